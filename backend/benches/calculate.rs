@@ -20,14 +20,7 @@ fn bench_calc() {
 	board.set_move(Position::new(7, 10), Piece::Min, None);
 
 
-	let mut solver = GomokuSolver::from(GomokuSolver {
-		board: board,
-		is_hint: None,
-		depth: 5,
-		captures: [0,0],
-		depth_entries: vec![0; 7],
-		player: Piece::Max
-	});
+	let mut solver = GomokuSolver::new(board, [0, 0], Piece::Max, 5);
 
 	let _ = solver.solve();
 }
@@ -0,0 +1,147 @@
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, time::Duration};
+
+use serde::Serialize;
+
+use crate::{error::GomokuError, game::Game, minimax, piece::Piece, position::Position};
+
+// Score drops, for the side that moved, from which a move stops counting as best.
+// An open three is worth 16, an open four 64.
+const INACCURACY: f32 = 4.0;
+const MISTAKE: f32 = 16.0;
+const BLUNDER: f32 = 64.0;
+// Attacker moves of the VCF/VCT search run before every search, so forced wins past
+// the search depth are still found. It shares the position's time budget with the search.
+const THREAT_DEPTH: usize = 8;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveClass {
+	Best,
+	Inaccuracy,
+	Mistake,
+	Blunder,
+	// the engine found a forced win the move let go
+	MissedWin,
+}
+
+// What the engine thinks of one move of a game. Scores are from black's side like
+// every other score, `score_drop` is from the side that moved.
+#[derive(Clone, Debug, Serialize)]
+pub struct MoveAnalysis {
+	pub player: Piece,
+	pub position: Position,
+	pub best_move: Position,
+	// search score of the position before the move
+	pub best_score: f32,
+	// moves to the end of the engine's line when `best_score` is a forced result
+	pub mate_in: Option<i32>,
+	// search score of the position after the move
+	pub score: f32,
+	// `Heuristic::get_heuristic` of the position after the move
+	pub static_score: f32,
+	pub score_drop: f32,
+	pub class: MoveClass,
+}
+
+// The search result of one position.
+pub struct Evaluation {
+	pub score: f32,
+	pub best_move: Option<Position>,
+	pub mate_in: Option<i32>,
+}
+
+fn evaluate(game: &Game, depth: usize, max_time: Option<Duration>, stop: &Arc<AtomicBool>) -> Result<Evaluation, GomokuError> {
+	if let Some(winner) = game.winner() {
+		return Ok(Evaluation {
+			score: if winner == Piece::Max {f32::INFINITY} else {-f32::INFINITY},
+			best_move: None,
+			mate_in: Some(0),
+		});
+	}
+
+	let mut solver = game.solver(depth);
+
+	solver.max_time = max_time.or(solver.max_time);
+	solver.threat_depth = Some(THREAT_DEPTH);
+	solver.set_stop_handle(stop.clone());
+
+	// the solver reports request errors wrapped in an `io::Error`
+	let res = solver.solve().map_err(|e| match e.get_ref().and_then(|inner| inner.downcast_ref::<GomokuError>()) {
+		Some(inner) => inner.clone(),
+		None => GomokuError::InvalidBoard(e.to_string()),
+	})?;
+
+	Ok(Evaluation {
+		score: res.score,
+		best_move: Some(res.position),
+		mate_in: minimax::resolve_mate_depth(&res, solver.depth),
+	})
+}
+
+// How much `player` lost by playing `played` instead of `best.best_move`, and what
+// kind of move that makes it.
+pub fn classify(player: Piece, played: Position, best: &Evaluation, after: &Evaluation) -> (f32, MoveClass) {
+	let sign = if player == Piece::Max {1.0} else {-1.0};
+	let (best_score, score) = (best.score * sign, after.score * sign);
+
+	// equal infinities would make the drop NaN
+	let drop = if best_score == score {0.0} else {(best_score - score).max(0.0)};
+
+	let class = if best.best_move == Some(played) || drop < INACCURACY {
+		MoveClass::Best
+	} else if best_score == f32::INFINITY {
+		MoveClass::MissedWin
+	} else if drop >= BLUNDER || score == -f32::INFINITY {
+		MoveClass::Blunder
+	} else if drop >= MISTAKE {
+		MoveClass::Mistake
+	} else {
+		MoveClass::Inaccuracy
+	};
+
+	(drop, class)
+}
+
+// Searches every position of `moves` played from `game` and rates each move against the
+// engine's choice. Searches run to `depth`, or for `max_time` each when given. Once `stop`
+// is set, the moves analysed so far are returned.
+pub fn analyze_game(mut game: Game, moves: &[Position], depth: usize, max_time: Option<Duration>, stop: &Arc<AtomicBool>) -> Result<Vec<MoveAnalysis>, GomokuError> {
+	let mut analysis = Vec::new();
+
+	if moves.is_empty() {
+		return Ok(analysis);
+	}
+
+	// Every position is searched once, to the same depth: its result rates both the move
+	// into it and the move out of it.
+	let mut before = evaluate(&game, depth, max_time, stop)?;
+
+	for pos in moves {
+		let player = game.player;
+		let position = game.play(*pos)?.position;
+		let static_score = game.heuristic().get_heuristic();
+		let after = evaluate(&game, depth, max_time, stop)?;
+
+		if stop.load(Ordering::Relaxed) {
+			break;
+		}
+
+		let (score_drop, class) = classify(player, position, &before, &after);
+
+		analysis.push(MoveAnalysis {
+			player,
+			position,
+			best_move: before.best_move.unwrap_or(position),
+			best_score: before.score,
+			mate_in: before.mate_in,
+			score: after.score,
+			static_score,
+			score_drop,
+			class,
+		});
+
+		before = after;
+	}
+
+	Ok(analysis)
+}
@@ -0,0 +1,188 @@
+// Gomocup / Piskvork brain. Speaks the stdin/stdout protocol of gomoku
+// tournament managers on top of the same solver the websocket server uses.
+// Search logs go to stderr, stdout only carries protocol replies.
+use std::{io::{self, BufRead, Write}, time::Duration};
+
+use backend::{board::{Board, MoveUndo}, minimax::GomokuSolver, pbrain::{self, Command, Info}, piece::{Piece, PieceWrap}, position::Position, ruleset::RuleKind};
+
+const MAX_DEPTH: usize = 20;
+// Turn time used until the manager sends `INFO timeout_turn`.
+const DEFAULT_TURN_MS: u64 = 5000;
+// Kept back from every turn for move generation and I/O.
+const TIME_MARGIN_MS: u64 = 100;
+// Share of the remaining match time a single turn may use.
+const TIME_LEFT_DIVISOR: u64 = 8;
+
+struct Brain {
+	board: Board,
+	captures: [usize; 2],
+	history: Vec<MoveUndo>,
+	// unknown until the first BEGIN, TURN or BOARD
+	me: Option<Piece>,
+	timeout_turn: u64,
+	time_left: Option<u64>,
+	rules: RuleKind,
+}
+
+impl Brain {
+	fn new() -> Brain {
+		Brain {
+			board: Board::new(),
+			captures: [0, 0],
+			history: Vec::new(),
+			me: None,
+			timeout_turn: DEFAULT_TURN_MS,
+			time_left: None,
+			// what managers play until they send `INFO rule`
+			rules: RuleKind::Freestyle,
+		}
+	}
+
+	fn reset(&mut self) {
+		self.board = Board::with_size(self.board.size());
+		self.captures = [0, 0];
+		self.history.clear();
+		self.me = None;
+	}
+
+	fn play(&mut self, pos: Position, player: Piece) -> Result<(), String> {
+		if self.board[&pos].is_piece() {
+			return Err(format!("square {},{} is occupied", pos.x, pos.y));
+		}
+
+		let capture_map = self.rules.ruleset().capture_map(&self.board, pos, player);
+		let undo = self.board.make_move(pos, player, Some(capture_map));
+
+		self.captures[player as usize] += undo.capture_count();
+		self.history.push(undo);
+
+		Ok(())
+	}
+
+	fn take_back(&mut self, pos: Position) -> Result<(), String> {
+		match self.history.last() {
+			Some(undo) if undo.position == pos => {
+				let undo = self.history.pop().unwrap();
+
+				self.captures[undo.player as usize] -= undo.capture_count();
+				self.board.unmake_move(&undo);
+
+				Ok(())
+			},
+			_ => Err(format!("{},{} is not the last move", pos.x, pos.y)),
+		}
+	}
+
+	fn turn_budget(&self) -> Duration {
+		let mut budget = self.timeout_turn;
+
+		if let Some(time_left) = self.time_left {
+			budget = budget.min(time_left / TIME_LEFT_DIVISOR);
+		}
+
+		Duration::from_millis(budget.saturating_sub(TIME_MARGIN_MS).max(1))
+	}
+
+	// Searches a move for `me`, plays it and returns it.
+	fn think(&mut self) -> Result<Position, String> {
+		let me = self.me.unwrap_or(Piece::Max);
+
+		let mut solver = GomokuSolver::new(self.board.clone(), self.captures, me, MAX_DEPTH);
+
+		solver.is_hint = Some(true);
+		solver.rules = self.rules.ruleset();
+		solver.max_time = Some(self.turn_budget());
+
+		let res = solver.solve().map_err(|e| e.to_string())?;
+
+		self.play(res.position, me)?;
+
+		Ok(res.position)
+	}
+}
+
+fn respond(text: &str) {
+	let mut stdout = io::stdout().lock();
+
+	let _ = writeln!(stdout, "{}", text);
+	let _ = stdout.flush();
+}
+
+fn respond_move(res: Result<Position, String>) {
+	match res {
+		Ok(pos) => respond(&format!("{},{}", pos.x, pos.y)),
+		Err(e) => respond(&format!("ERROR {}", e)),
+	}
+}
+
+fn main() {
+	let mut brain = Brain::new();
+	let stdin = io::stdin();
+	let mut lines = stdin.lock().lines();
+
+	while let Some(Ok(line)) = lines.next() {
+		let command = match pbrain::parse_command(&brain.board, &line) {
+			Ok(command) => command,
+			Err(e) => {
+				respond(&format!("ERROR {}", e));
+				continue;
+			},
+		};
+
+		match command {
+			Command::Start(size) => {
+				brain.board = Board::with_size(size);
+				brain.reset();
+				respond("OK");
+			},
+			Command::Restart => {
+				brain.reset();
+				respond("OK");
+			},
+			Command::Begin => {
+				brain.me = Some(Piece::Max);
+				respond_move(brain.think());
+			},
+			Command::Turn(pos) => {
+				// Answering the very first move of the game makes us the second player.
+				let me = *brain.me.get_or_insert(Piece::Min);
+
+				respond_move(brain.play(pos, me.get_opposite()).and_then(|_| brain.think()));
+			},
+			Command::Board => {
+				brain.reset();
+
+				let res = pbrain::read_board(&brain.board, &mut lines).and_then(|stones| {
+					// Equal stone counts mean we are the side that started.
+					let me = if stones.len() % 2 == 0 {Piece::Max} else {Piece::Min};
+					brain.me = Some(me);
+
+					for (pos, own) in stones {
+						brain.play(pos, if own {me} else {me.get_opposite()})?;
+					}
+
+					brain.think()
+				});
+
+				respond_move(res);
+			},
+			Command::Takeback(pos) => match brain.take_back(pos) {
+				Ok(()) => respond("OK"),
+				Err(e) => respond(&format!("ERROR {}", e)),
+			},
+			Command::Info(info) => match info {
+				Info::TimeoutTurn(ms) => brain.timeout_turn = ms.max(TIME_MARGIN_MS + 1),
+				Info::TimeLeft(ms) => brain.time_left = Some(ms),
+				Info::Rule(rules) => brain.rules = rules,
+				Info::Ignored => (),
+			},
+			Command::About => respond(&format!(
+				"name=\"ft_gomoku\", version=\"{}\", author=\"Alpha1337k\"",
+				env!("CARGO_PKG_VERSION")
+			)),
+			Command::End => return,
+			Command::Empty => (),
+			Command::Unknown(command) => respond(&format!("UNKNOWN command '{}'", command)),
+		}
+	}
+}
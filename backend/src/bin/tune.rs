@@ -0,0 +1,381 @@
+// Texel tuning of the evaluation weights. Every position of a corpus of finished games
+// is labelled with the game's result, the weights are then moved one entry at a time
+// while that lowers the squared error between the results and the win chance predicted
+// from `Heuristic::get_heuristic`. Every fifth game is left out of the tuning, its
+// positions show whether the new weights also predict games they were not tuned on.
+//
+//   tune [options] [corpus files...]
+//
+// Corpus files are `.psq` or `.sgf` records, anything else is read as one game per line
+// in the move list notation. Progress goes to stderr, the report to stdout.
+use std::{fs, path::Path, process};
+
+use backend::{
+	board::Board,
+	game::Game,
+	heuristic::{EvalWeights, Heuristic},
+	notation::{Notation, Record},
+	opening::OpeningRule,
+	piece::{Piece, PieceWrap},
+	position::{Position, DEFAULT_SIZE},
+	ruleset::RuleKind,
+};
+
+const USAGE: &str = "usage: tune [--weights FILE] [--out FILE] [--rules RULES] [--size N]
+            [--self-play GAMES] [--depth N] [--random-plies N] [--seed N] [--save-corpus FILE]
+            [--iterations N] [--step X] [corpus files...]";
+
+// Opening moves of a self-play game are played at random, at most this far from the centre.
+const RANDOM_RADIUS: usize = 3;
+// One game out of this many is held out of the tuning.
+const HOLDOUT_EVERY: usize = 5;
+
+struct Options {
+	weights: EvalWeights,
+	out: String,
+	rules: RuleKind,
+	size: usize,
+	self_play: usize,
+	depth: usize,
+	random_plies: usize,
+	seed: u64,
+	save_corpus: Option<String>,
+	iterations: usize,
+	step: f32,
+	corpus: Vec<String>,
+}
+
+// A position and the result of its game for black: 1 won, 0 lost, 0.5 drawn.
+struct Sample {
+	board: Board,
+	captures: [usize; 2],
+	result: f32,
+}
+
+fn parse_options() -> Result<Options, String> {
+	let mut options = Options {
+		weights: EvalWeights::default(),
+		out: "weights.json".to_string(),
+		rules: RuleKind::default(),
+		size: DEFAULT_SIZE,
+		self_play: 0,
+		depth: 2,
+		random_plies: 4,
+		seed: 1,
+		save_corpus: None,
+		iterations: 50,
+		step: 1.0,
+		corpus: Vec::new(),
+	};
+	let mut args = std::env::args().skip(1);
+
+	while let Some(arg) = args.next() {
+		if !arg.starts_with("--") {
+			options.corpus.push(arg);
+			continue;
+		}
+
+		let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+		let invalid = || format!("invalid value for {}: {}", arg, value);
+
+		match arg.as_str() {
+			"--weights" => {
+				let text = fs::read_to_string(&value).map_err(|e| format!("could not read {}: {}", value, e))?;
+				options.weights = serde_json::from_str(&text).map_err(|e| format!("invalid weights in {}: {}", value, e))?;
+			},
+			"--out" => options.out = value,
+			"--rules" => options.rules = serde_json::from_value(serde_json::Value::String(value.clone())).map_err(|_| format!("unknown rules {}", value))?,
+			"--size" => options.size = value.parse().map_err(|_| invalid())?,
+			"--self-play" => options.self_play = value.parse().map_err(|_| invalid())?,
+			"--depth" => options.depth = value.parse().map_err(|_| invalid())?,
+			"--random-plies" => options.random_plies = value.parse().map_err(|_| invalid())?,
+			"--seed" => options.seed = value.parse().map_err(|_| invalid())?,
+			"--save-corpus" => options.save_corpus = Some(value),
+			"--iterations" => options.iterations = value.parse().map_err(|_| invalid())?,
+			"--step" => options.step = value.parse().map_err(|_| invalid())?,
+			_ => return Err(format!("unknown option {}\n{}", arg, USAGE)),
+		}
+	}
+
+	Board::check_size(options.size).map_err(|e| e.to_string())?;
+
+	Ok(options)
+}
+
+struct Random(u64);
+
+impl Random {
+	fn next(&mut self, bound: usize) -> usize {
+		self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+		let mut z = self.0;
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+		((z ^ (z >> 31)) % bound as u64) as usize
+	}
+}
+
+// Plays a random free cell around the centre, false when none of them is allowed.
+fn play_random(game: &mut Game, random: &mut Random) -> bool {
+	let size = game.board.size();
+	let low = (size / 2).saturating_sub(RANDOM_RADIUS);
+	let high = (size / 2 + RANDOM_RADIUS).min(size - 1);
+	let mut cells: Vec<Position> = (low..=high)
+		.flat_map(|y| (low..=high).map(move |x| Position::with_size(x, y, size)))
+		.filter(|pos| game.board[pos].is_empty())
+		.collect();
+
+	while !cells.is_empty() {
+		let pos = cells.swap_remove(random.next(cells.len()));
+
+		// forbidden, draw again
+		if game.play(pos).is_ok() {
+			return true;
+		}
+	}
+
+	false
+}
+
+// A game of the engine against itself, from a few random moves around the centre.
+fn self_play(options: &Options, random: &mut Random) -> Result<Game, String> {
+	let mut game = Game::new(options.size, options.rules, OpeningRule::Free, None).map_err(|e| e.to_string())?;
+	let mut random_plies = options.random_plies;
+
+	game.weights = options.weights;
+
+	while game.winner().is_none() && game.moves().len() < options.size * options.size {
+		if game.moves().len() < random_plies {
+			if play_random(&mut game, random) {
+				continue;
+			}
+
+			// no cell left around the centre, the engine plays on
+			random_plies = 0;
+		}
+
+		let position = game.solver(options.depth).solve().map_err(|e| format!("self-play search failed: {}", e))?.position;
+
+		if game.play(position).is_err() {
+			break;
+		}
+	}
+
+	Ok(game)
+}
+
+fn read_corpus(path: &str, options: &Options) -> Result<Vec<Record>, String> {
+	let text = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+	let extension = Path::new(path).extension().and_then(|e| e.to_str()).map(str::to_lowercase);
+
+	let notation = match extension.as_deref() {
+		Some("psq") => Some(Notation::Psq),
+		Some("sgf") => Some(Notation::Sgf),
+		_ => None,
+	};
+
+	match notation {
+		Some(notation) => Ok(vec![notation.read(&text, options.size).map_err(|e| format!("{}: {}", path, e))?]),
+		None => text.lines()
+			.enumerate()
+			.filter(|(_, line)| !line.trim().is_empty())
+			.map(|(i, line)| Notation::Moves.read(line, options.size).map_err(|e| format!("{}:{}: {}", path, i + 1, e)))
+			.collect(),
+	}
+}
+
+// The position after every move of `record`, labelled with how the game ended.
+fn label_positions(record: &Record, options: &Options) -> Result<Vec<Sample>, String> {
+	let mut game = Game::new(record.size, options.rules, OpeningRule::Free, None).map_err(|e| e.to_string())?;
+	let mut positions = Vec::with_capacity(record.moves.len());
+
+	for pos in &record.moves {
+		game.play(*pos).map_err(|e| e.to_string())?;
+		positions.push((game.board.clone(), game.captures));
+	}
+
+	let result = match game.winner() {
+		Some(Piece::Max) => 1.0,
+		Some(_) => 0.0,
+		None => 0.5,
+	};
+
+	Ok(positions.into_iter().map(|(board, captures)| Sample { board, captures, result }).collect())
+}
+
+fn sigmoid(k: f64, score: f64) -> f64 {
+	1.0 / (1.0 + (-k * score).exp())
+}
+
+// Static scores of the samples, `None` for positions the heuristic already calls won.
+fn scores(samples: &[Sample], options: &Options, weights: EvalWeights) -> Vec<Option<f64>> {
+	samples.iter()
+		.map(|sample| {
+			let score = Heuristic::with_weights(&sample.board, &sample.captures, options.rules.ruleset(), weights).get_heuristic();
+
+			score.is_finite().then_some(score as f64)
+		})
+		.collect()
+}
+
+// Mean squared error between the results and the predicted win chances.
+fn error(samples: &[Sample], scores: &[Option<f64>], k: f64) -> f64 {
+	let (sum, count) = samples.iter().zip(scores)
+		.filter_map(|(sample, score)| score.map(|score| (sample.result as f64 - sigmoid(k, score)).powi(2)))
+		.fold((0.0, 0usize), |(sum, count), e| (sum + e, count + 1));
+
+	if count == 0 {0.0} else {sum / count as f64}
+}
+
+// The scaling of scores into win chances that fits the starting weights best.
+fn fit_k(samples: &[Sample], scores: &[Option<f64>]) -> f64 {
+	let mut best = (f64::MAX, 0.0);
+	let mut step = 0.01;
+	let mut center = 0.5;
+
+	for _ in 0..3 {
+		for i in -50..=50 {
+			let k = center + i as f64 * step;
+			let e = error(samples, scores, k);
+
+			if k > 0.0 && e < best.0 {
+				best = (e, k);
+			}
+		}
+
+		center = best.1;
+		step /= 10.0;
+	}
+
+	best.1
+}
+
+// The tuned entries with their names. Move ordering weights do not change static scores.
+fn parameters(weights: &mut EvalWeights) -> Vec<(String, &mut f32)> {
+	let tables = [
+		("captures", &mut weights.captures),
+		("open", &mut weights.open),
+		("half_open", &mut weights.half_open),
+		("closed", &mut weights.closed),
+	];
+	let mut parameters: Vec<(String, &mut f32)> = tables.into_iter()
+		.flat_map(|(name, table)| table.iter_mut().enumerate().map(move |(i, w)| (format!("{}[{}]", name, i), w)))
+		.collect();
+
+	parameters.push(("centrality".to_string(), &mut weights.centrality));
+	parameters
+}
+
+fn run(options: Options) -> Result<(), String> {
+	let mut records = Vec::new();
+
+	for path in &options.corpus {
+		records.extend(read_corpus(path, &options)?);
+	}
+
+	let mut random = Random(options.seed);
+	let mut played = Vec::new();
+
+	for i in 0..options.self_play {
+		let game = self_play(&options, &mut random)?;
+
+		eprintln!("tune: self-play game {}/{}: {} moves, winner {:?}", i + 1, options.self_play, game.moves().len(), game.winner());
+		played.push(Record::from_game(&game));
+	}
+
+	if let Some(path) = &options.save_corpus {
+		let text: String = played.iter().map(|record| Notation::Moves.write(record) + "\n").collect();
+
+		fs::write(path, text).map_err(|e| format!("could not write {}: {}", path, e))?;
+	}
+
+	records.extend(played);
+
+	let mut samples = Vec::new();
+	let mut held_out = Vec::new();
+
+	for (i, record) in records.iter().enumerate() {
+		let positions = label_positions(record, &options)?;
+
+		if i % HOLDOUT_EVERY == HOLDOUT_EVERY - 1 {
+			held_out.extend(positions);
+		} else {
+			samples.extend(positions);
+		}
+	}
+
+	if samples.is_empty() {
+		return Err(format!("no positions to tune on\n{}", USAGE));
+	}
+
+	let mut weights = options.weights;
+	let k = fit_k(&samples, &scores(&samples, &options, weights));
+	let initial = error(&samples, &scores(&samples, &options, weights), k);
+	let mut best = initial;
+
+	eprintln!("tune: {} positions from {} games, k = {:.5}, error {:.6}", samples.len(), records.len(), k, initial);
+
+	for iteration in 1..=options.iterations {
+		let mut improved = false;
+		let count = parameters(&mut weights).len();
+
+		for i in 0..count {
+			for delta in [options.step, -options.step] {
+				let mut candidate = weights;
+
+				*parameters(&mut candidate)[i].1 += delta;
+
+				let e = error(&samples, &scores(&samples, &options, candidate), k);
+
+				if e < best {
+					weights = candidate;
+					best = e;
+					improved = true;
+					break;
+				}
+			}
+		}
+
+		eprintln!("tune: iteration {}: error {:.6}", iteration, best);
+
+		if !improved {
+			break;
+		}
+	}
+
+	let json = serde_json::to_string_pretty(&weights).map_err(|e| e.to_string())?;
+
+	fs::write(&options.out, json + "\n").map_err(|e| format!("could not write {}: {}", options.out, e))?;
+
+	println!("positions: {}, {} held out", samples.len(), held_out.len());
+	println!("k: {:.5}", k);
+	println!("error before: {:.6}", initial);
+	println!("error after:  {:.6}", best);
+
+	if held_out.is_empty() {
+		println!("held-out error: no held-out games, needs {} games or more", HOLDOUT_EVERY);
+	} else {
+		println!("held-out error before: {:.6}", error(&held_out, &scores(&held_out, &options, options.weights), k));
+		println!("held-out error after:  {:.6}", error(&held_out, &scores(&held_out, &options, weights), k));
+	}
+
+	let mut before = options.weights;
+
+	for ((name, old), (_, new)) in parameters(&mut before).into_iter().zip(parameters(&mut weights)) {
+		if old != new {
+			println!("{:14} {:>8} -> {}", name, *old, *new);
+		}
+	}
+
+	println!("weights written to {}", options.out);
+
+	Ok(())
+}
+
+fn main() {
+	let res = parse_options().and_then(run);
+
+	if let Err(e) = res {
+		eprintln!("tune: {}", e);
+		process::exit(1);
+	}
+}
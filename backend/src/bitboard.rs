@@ -0,0 +1,393 @@
+use std::fmt;
+
+use crate::{board::{Board, BoardIterator, GameBoard, MoveUndo}, piece::{Piece, PieceWrap}, position::{Position, DEFAULT_SIZE, MAX_SIZE}, zobrist};
+
+// The layout fits the largest board, smaller boards use its top left corner.
+const SIZE: usize = MAX_SIZE;
+// Every row carries one always-empty guard bit, so shifting a row past its
+// edge lands on the guard instead of wrapping into the next row. This lets a
+// single layout answer all four directions without rotated copies.
+const STRIDE: usize = SIZE + 1;
+const CELLS: usize = SIZE * STRIDE;
+const WORDS: usize = CELLS.div_ceil(64);
+
+// Shift distances for x, y, tlbr and trbl, in the same order as the heuristic directions.
+const SHIFTS: [usize; 4] = [1, STRIDE, STRIDE + 1, STRIDE - 1];
+
+// Index offset of the first and second side of each direction, matching `Board::get_captures`.
+const OFFSETS: [[isize; 2]; 4] = [
+	[-1, 1],
+	[-(STRIDE as isize), STRIDE as isize],
+	[-(STRIDE as isize + 1), STRIDE as isize + 1],
+	[STRIDE as isize - 1, -(STRIDE as isize - 1)],
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Bits([u64; WORDS]);
+
+// Cells of a board with side length `size`.
+const fn board_mask(size: usize) -> Bits {
+	let mut words = [0u64; WORDS];
+	let mut i = 0;
+
+	while i < CELLS {
+		if i % STRIDE < size && i / STRIDE < size {
+			words[i / 64] |= 1 << (i % 64);
+		}
+		i += 1;
+	}
+
+	Bits(words)
+}
+
+const BOARD_MASK: Bits = board_mask(SIZE);
+
+impl Bits {
+	fn index(pos: &Position) -> usize {
+		pos.y * STRIDE + pos.x
+	}
+
+	// The cell of bit `idx` on a board with side length `size`.
+	fn position(idx: usize, size: usize) -> Position {
+		Position::with_size(idx % STRIDE, idx / STRIDE, size)
+	}
+
+	pub fn test(&self, pos: &Position) -> bool {
+		let idx = Self::index(pos);
+
+		self.0[idx / 64] >> (idx % 64) & 1 == 1
+	}
+
+	pub fn set(&mut self, pos: &Position) {
+		let idx = Self::index(pos);
+
+		self.0[idx / 64] |= 1 << (idx % 64);
+	}
+
+	pub fn clear(&mut self, pos: &Position) {
+		let idx = Self::index(pos);
+
+		self.0[idx / 64] &= !(1 << (idx % 64));
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.iter().all(|w| *w == 0)
+	}
+
+	pub fn count(&self) -> usize {
+		self.0.iter().map(|w| w.count_ones() as usize).sum()
+	}
+
+	pub fn and(&self, other: &Bits) -> Bits {
+		let mut rv = *self;
+		for i in 0..WORDS {
+			rv.0[i] &= other.0[i];
+		}
+		rv
+	}
+
+	pub fn or(&self, other: &Bits) -> Bits {
+		let mut rv = *self;
+		for i in 0..WORDS {
+			rv.0[i] |= other.0[i];
+		}
+		rv
+	}
+
+	// Cells on the board that are not set.
+	pub fn not(&self) -> Bits {
+		let mut rv = *self;
+		for i in 0..WORDS {
+			rv.0[i] = !rv.0[i] & BOARD_MASK.0[i];
+		}
+		rv
+	}
+
+	// Moves every bit from cell `i` to cell `i + n`.
+	pub fn shl(&self, n: usize) -> Bits {
+		let mut rv = Bits::default();
+		let (words, bits) = (n / 64, n % 64);
+
+		for i in (words..WORDS).rev() {
+			rv.0[i] = self.0[i - words] << bits;
+			if bits != 0 && i > words {
+				rv.0[i] |= self.0[i - words - 1] >> (64 - bits);
+			}
+		}
+
+		rv.and(&BOARD_MASK)
+	}
+
+	// Moves every bit from cell `i` to cell `i - n`.
+	pub fn shr(&self, n: usize) -> Bits {
+		let mut rv = Bits::default();
+		let (words, bits) = (n / 64, n % 64);
+
+		for i in 0..WORDS - words {
+			rv.0[i] = self.0[i + words] >> bits;
+			if bits != 0 && i + words + 1 < WORDS {
+				rv.0[i] |= self.0[i + words + 1] << (64 - bits);
+			}
+		}
+
+		rv.and(&BOARD_MASK)
+	}
+
+	// Sets cell `i` wherever cell `i + offset` is set.
+	pub fn look(&self, offset: isize) -> Bits {
+		if offset >= 0 {
+			self.shr(offset as usize)
+		} else {
+			self.shl(offset.unsigned_abs())
+		}
+	}
+
+	// The set cells, as positions on a board with side length `size`.
+	pub fn positions(&self, size: usize) -> Vec<Position> {
+		let mut rv = Vec::with_capacity(self.count());
+
+		for (w, word) in self.0.iter().enumerate() {
+			let mut word = *word;
+			while word != 0 {
+				let bit = word.trailing_zeros() as usize;
+				rv.push(Self::position(w * 64 + bit, size));
+				word &= word - 1;
+			}
+		}
+
+		rv
+	}
+}
+
+// Bit-per-cell board with one set per player. Shares `GameBoard` with `Board`,
+// so the two can be swapped.
+#[derive(Clone)]
+pub struct BitBoard {
+	stones: [Bits; 2],
+	size: usize,
+	mask: Bits,
+	hash: u64,
+}
+
+impl Default for BitBoard {
+	fn default() -> Self {
+		BitBoard::with_size(DEFAULT_SIZE)
+	}
+}
+
+impl BitBoard {
+	pub fn new() -> BitBoard {
+		BitBoard::default()
+	}
+
+	pub fn with_size(size: usize) -> BitBoard {
+		Board::check_size(size).unwrap();
+
+		BitBoard {
+			stones: [Bits::default(); 2],
+			size,
+			mask: board_mask(size),
+			hash: 0,
+		}
+	}
+
+	pub fn from_board(board: &Board) -> BitBoard {
+		let mut rv = BitBoard::with_size(board.size());
+
+		for pos in board {
+			if board[&pos].is_piece() {
+				rv.put(&pos, board[&pos]);
+			}
+		}
+
+		rv
+	}
+
+	pub fn to_board(&self) -> Board {
+		let mut board = Board::with_size(self.size);
+
+		for player in [Piece::Max, Piece::Min] {
+			for pos in self.stones(player).positions(self.size) {
+				board.set_move(pos, player, Some(0));
+			}
+		}
+
+		board
+	}
+
+	pub fn stones(&self, player: Piece) -> &Bits {
+		&self.stones[player as usize]
+	}
+
+	pub fn empty(&self) -> Bits {
+		self.stones[0].or(&self.stones[1]).not().and(&self.mask)
+	}
+
+	pub fn get(&self, x: usize, y: usize) -> &Piece {
+		&self[&Position::with_size(x, y, self.size)]
+	}
+
+	pub fn len(&self) -> usize {
+		self.size * self.size
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	// Cells that start (lowest index) a run of `n` stones of `player` in any direction.
+	fn runs(&self, player: Piece, n: usize) -> [Bits; 4] {
+		let own = self.stones(player);
+
+		SHIFTS.map(|shift| {
+			let mut run = *own;
+			for i in 1..n {
+				run = run.and(&own.shr(i * shift));
+			}
+			run
+		})
+	}
+
+	pub fn has_five(&self, player: Piece) -> bool {
+		self.runs(player, 5).iter().any(|r| !r.is_empty())
+	}
+
+	// Start cells of every five-or-longer run of `player`.
+	pub fn fives(&self, player: Piece) -> Bits {
+		self.runs(player, 5).iter().fold(Bits::default(), |acc, r| acc.or(r))
+	}
+
+	// Start cells of `_XXXX_` runs of `player`.
+	pub fn open_fours(&self, player: Piece) -> Bits {
+		let empty = self.empty();
+		let runs = self.runs(player, 4);
+		let mut rv = Bits::default();
+
+		for (i, shift) in SHIFTS.iter().enumerate() {
+			rv = rv.or(&runs[i].and(&empty.shl(*shift)).and(&empty.shr(4 * shift)));
+		}
+
+		rv
+	}
+
+	// Empty cells where `player` would capture at least one pair.
+	pub fn capture_moves(&self, player: Piece) -> Bits {
+		let own = self.stones(player);
+		let opp = self.stones(player.get_opposite());
+		let mut rv = Bits::default();
+
+		for offset in OFFSETS.iter().flatten() {
+			rv = rv.or(&opp.look(*offset).and(&opp.look(2 * offset)).and(&own.look(3 * offset)));
+		}
+
+		rv.and(&self.empty())
+	}
+}
+
+impl GameBoard for BitBoard {
+	fn size(&self) -> usize {
+		self.size
+	}
+
+	fn hash(&self) -> u64 {
+		self.hash
+	}
+
+	// Same bit layout as `Board::get_captures`: two bits per direction, in `OFFSETS` order.
+	fn get_captures(&self, pos: Position, player: Piece) -> u8 {
+		let own = self.stones(player);
+		let opp = self.stones(player.get_opposite());
+		let mut rv = 0;
+
+		for (i, offsets) in OFFSETS.iter().enumerate() {
+			for (side, offset) in offsets.iter().enumerate() {
+				if opp.look(*offset).test(&pos) && opp.look(2 * offset).test(&pos) && own.look(3 * offset).test(&pos) {
+					rv |= 1 << (i * 2 + side);
+				}
+			}
+		}
+
+		rv
+	}
+
+	fn make_move(&mut self, pos: Position, player: Piece, capture_map: Option<u8>) -> MoveUndo {
+		if self[&pos].is_piece() {
+			panic!();
+		}
+
+		let captures = capture_map.unwrap_or_else(|| self.get_captures(pos, player));
+
+		self.put(&pos, player);
+
+		let mut undo = MoveUndo {
+			position: pos,
+			player,
+			captured: Vec::new(),
+		};
+
+		for (i, offsets) in OFFSETS.iter().enumerate() {
+			for (side, offset) in offsets.iter().enumerate() {
+				if captures & (1 << (i * 2 + side)) == 0 {
+					continue;
+				}
+
+				for n in 1..3 {
+					let captured = Bits::position(Bits::index(&pos).wrapping_add_signed(n * offset), self.size);
+
+					self.put(&captured, Piece::Empty);
+					undo.captured.push(captured);
+				}
+			}
+		}
+
+		undo
+	}
+
+	fn unmake_move(&mut self, undo: &MoveUndo) {
+		for captured in &undo.captured {
+			self.put(captured, undo.player.get_opposite());
+		}
+
+		self.put(&undo.position, Piece::Empty);
+	}
+
+	fn put(&mut self, pos: &Position, piece: Piece) {
+		self.hash ^= zobrist::piece_key(pos, self[pos]) ^ zobrist::piece_key(pos, piece);
+
+		self.stones[0].clear(pos);
+		self.stones[1].clear(pos);
+
+		if piece.is_piece() {
+			self.stones[piece as usize].set(pos);
+		}
+	}
+}
+
+impl std::ops::Index<&Position> for BitBoard {
+	type Output = Piece;
+
+	fn index(&self, idx: &Position) -> &Piece {
+		if self.stones[0].test(idx) {
+			&Piece::Max
+		} else if self.stones[1].test(idx) {
+			&Piece::Min
+		} else {
+			&Piece::Empty
+		}
+	}
+}
+
+impl<'a> IntoIterator for &'a BitBoard {
+	type Item = Position;
+	type IntoIter = BoardIterator;
+
+	fn into_iter(self) -> Self::IntoIter {
+		BoardIterator::new(self.size)
+	}
+}
+
+impl fmt::Display for BitBoard {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.to_board())
+	}
+}
@@ -5,31 +5,122 @@ use std::{fmt};
 
 use serde::Serialize;
 use serde_json::Value;
-use crate::{piece::{Piece, PieceWrap}, position::Position};
+use crate::{error::GomokuError, piece::{Piece, PieceWrap}, position::{Position, DEFAULT_SIZE, MAX_SIZE, MIN_SIZE}, zobrist};
+
+// A played stone and the opponent stones it removed, enough to take the move back.
+#[derive(Clone, Debug, Serialize)]
+pub struct MoveUndo {
+	pub position: Position,
+	pub player: Piece,
+	pub captured: Vec<Position>,
+}
+
+impl MoveUndo {
+	pub fn capture_count(&self) -> usize {
+		self.captured.len() / 2
+	}
+}
+
+// What the engine needs from a board, so `Board` and `BitBoard` can stand in for each other.
+pub trait GameBoard: for<'a> std::ops::Index<&'a Position, Output = Piece> {
+	fn size(&self) -> usize;
+	fn hash(&self) -> u64;
+	// The pairs `player` would capture by playing `pos`, two bits per direction.
+	fn get_captures(&self, pos: Position, player: Piece) -> u8;
+	// Writes a single cell, keeping the hash up to date. Stands in for `IndexMut`,
+	// which a bit per cell cannot hand out.
+	fn put(&mut self, pos: &Position, piece: Piece);
+	fn make_move(&mut self, pos: Position, player: Piece, capture_map: Option<u8>) -> MoveUndo;
+	fn unmake_move(&mut self, undo: &MoveUndo);
+
+	fn set_move(&mut self, pos: Position, player: Piece, capture_map: Option<u8>) -> usize {
+		self.make_move(pos, player, capture_map).capture_count()
+	}
+
+	// The cell `x`, `y` of this board, `None` when it lies outside.
+	fn position(&self, x: usize, y: usize) -> Option<Position> {
+		if x >= self.size() || y >= self.size() {
+			return None;
+		}
+
+		Some(Position::with_size(x, y, self.size()))
+	}
+}
 
 #[derive(Clone, Serialize)]
 pub struct Board {
 	data: Vec<Piece>,
+	size: usize,
+	// Zobrist key of the stones on the board. Kept up to date by `set_move`,
+	// writes through `IndexMut` bypass it.
+	#[serde(skip)]
+	hash: u64,
 }
 
 impl Board {
 	pub fn new() -> Board {
+		Self::with_size(DEFAULT_SIZE)
+	}
+
+	pub fn with_size(size: usize) -> Board {
+		Self::check_size(size).unwrap();
+
 		Board { 
-			data: vec![Piece::Empty; 19 * 19],
+			data: vec![Piece::Empty; size * size],
+			size,
+			hash: 0,
+		}
+	}
+
+	pub fn check_size(size: usize) -> Result<(), GomokuError> {
+		if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+			return Err(GomokuError::InvalidBoard(format!("unsupported board size {}, expected {} to {}", size, MIN_SIZE, MAX_SIZE)));
 		}
+
+		Ok(())
 	}
 
-	pub fn from_map(board_map: &serde_json::Map<String, Value>) -> Board {
-		let mut board =  Board { 
-			data: vec![Piece::Empty; 19 * 19],
-		 };
+	pub fn from_map(board_map: &serde_json::Map<String, Value>, size: usize) -> Result<Board, GomokuError> {
+		Self::check_size(size)?;
+
+		let mut board = Self::with_size(size);
 
 		 for (key, value) in board_map {
-			let key_int = key.parse::<usize>().unwrap();
-			board.data[key_int] = value.as_u64().unwrap().try_into().unwrap_or_default();
+			let key_int = key.parse::<usize>()
+				.ok()
+				.filter(|k| *k < board.data.len())
+				.ok_or_else(|| GomokuError::InvalidBoard(format!("bad cell index '{}'", key)))?;
+
+			board.data[key_int] = match value.as_i64() {
+				Some(-1) => Piece::Empty,
+				Some(v) if v >= 0 => (v as u64).try_into()
+					.map_err(|_| GomokuError::InvalidBoard(format!("bad piece {} at cell {}", value, key)))?,
+				_ => return Err(GomokuError::InvalidBoard(format!("bad piece {} at cell {}", value, key))),
+			};
+		}
+
+		board.hash = board.compute_hash();
+
+		return Ok(board);
+	}
+
+	pub fn hash(&self) -> u64 {
+		self.hash
+	}
+
+	fn compute_hash(&self) -> u64 {
+		let mut hash = 0;
+
+		for pos in self {
+			hash ^= zobrist::piece_key(&pos, self[&pos]);
 		}
 
-		return board;
+		hash
+	}
+
+	fn put(&mut self, pos: &Position, piece: Piece) {
+		self.hash ^= zobrist::piece_key(pos, self[pos]) ^ zobrist::piece_key(pos, piece);
+		self[pos] = piece;
 	}
 
 	pub fn get_diff(b1: &Board, b2: &Board) -> Vec<Position> {
@@ -66,27 +157,33 @@ impl Board {
 			}
 		}
 
-		println!("GET CAPTURES RES: {}, P: {}", rv, pos);
-
 		return rv;
 	}
 
 	pub fn set_move(&mut self, pos: Position, player: Piece, capture_map: Option<u8>) -> usize {
+		self.make_move(pos, player, capture_map).capture_count()
+	}
+
+	pub fn make_move(&mut self, pos: Position, player: Piece, capture_map: Option<u8>) -> MoveUndo {
 		if self[&pos].is_piece() {
 			panic!();
 		}
 
-		self[&pos] = player;
+		self.put(&pos, player);
+
+		let mut undo = MoveUndo {
+			position: pos,
+			player,
+			captured: Vec::new(),
+		};
 
 		if capture_map.is_some_and(|x| x == 0) {
-			return 0;
+			return undo;
 		}
 
 		let mut captures = capture_map.unwrap_or_else(|| Self::get_captures(&self, pos, player));
 		let captures_store = captures;
 
-		let mut capture_count = 0;
-
 		let maps = [
 			[[-1, 0], [-2, 0]],
 			[[1, 0], [2, 0]],
@@ -107,27 +204,49 @@ impl Board {
 
 				if pos.clone().relocate(map[0][0], map[0][1]).is_ok_and(|x| self[x] == player.get_opposite()) && 
 					pos.clone().relocate(map[1][0], map[1][1]).is_ok_and(|x| self[x] == player.get_opposite()) {
-					self[&pos.clone().relocate(map[0][0], map[0][1]).unwrap()] = Piece::Empty;
-					self[&pos.clone().relocate(map[1][0], map[1][1]).unwrap()] = Piece::Empty;
+					for offset in map {
+						let captured = *pos.clone().relocate(offset[0], offset[1]).unwrap();
+
+						self.put(&captured, Piece::Empty);
+						undo.captured.push(captured);
+					}
 				} else {
 					println!("\nFAILED CAPTURE AT POS: {} ({})", pos, captures_store);
 					dbg!(player, map[0], map[1]);
 					println!("{}", self);
 					panic!();
 				}
-
-				capture_count += 1;
-
 			}
 			captures >>= 1;
 			map_idx += 1;
 		}
 
-		capture_count
+		undo
+	}
+
+	pub fn unmake_move(&mut self, undo: &MoveUndo) {
+		for captured in &undo.captured {
+			self.put(captured, undo.player.get_opposite());
+		}
+
+		self.put(&undo.position, Piece::Empty);
 	}
 
 	pub fn get(&self, x: usize, y: usize) -> &Piece {
-		return &self.data[y * 19 + x];
+		&self.data[y * self.size + x]
+	}
+
+	pub fn size(&self) -> usize {
+		self.size
+	}
+
+	// The cell `x`, `y` of this board, `None` when it lies outside.
+	pub fn position(&self, x: usize, y: usize) -> Option<Position> {
+		if x >= self.size || y >= self.size {
+			return None;
+		}
+
+		Some(Position::with_size(x, y, self.size))
 	}
 
 	pub fn len(&self) -> usize {
@@ -135,22 +254,55 @@ impl Board {
 	}
 }
 
+impl GameBoard for Board {
+	fn size(&self) -> usize {
+		Board::size(self)
+	}
+
+	fn hash(&self) -> u64 {
+		Board::hash(self)
+	}
+
+	fn get_captures(&self, pos: Position, player: Piece) -> u8 {
+		Board::get_captures(self, pos, player)
+	}
+
+	fn put(&mut self, pos: &Position, piece: Piece) {
+		Board::put(self, pos, piece)
+	}
+
+	fn make_move(&mut self, pos: Position, player: Piece, capture_map: Option<u8>) -> MoveUndo {
+		Board::make_move(self, pos, player, capture_map)
+	}
+
+	fn unmake_move(&mut self, undo: &MoveUndo) {
+		Board::unmake_move(self, undo)
+	}
+}
+
 impl std::ops::Index<&Position> for Board {
     type Output = Piece;
 
     fn index(&self, idx: &Position) -> &Piece {
-		return &self.data[idx.x + idx.y * 19];
+		return &self.data[idx.x + idx.y * self.size];
     }
 }
 
 impl std::ops::IndexMut<&Position> for Board {
     fn index_mut(&mut self, idx: &Position) -> &mut Piece {
-		return &mut self.data[idx.x + idx.y * 19];
+		return &mut self.data[idx.x + idx.y * self.size];
     }
 }
 
 pub struct BoardIterator {
-	index: usize
+	index: usize,
+	size: usize,
+}
+
+impl BoardIterator {
+	pub(crate) fn new(size: usize) -> BoardIterator {
+		BoardIterator { index: 0, size }
+	}
 }
 
 impl<'a> IntoIterator for &'a Board {
@@ -158,9 +310,7 @@ impl<'a> IntoIterator for &'a Board {
     type IntoIter = BoardIterator;
 
     fn into_iter(self) -> Self::IntoIter {
-        BoardIterator {
-            index: 0,
-        }
+        BoardIterator::new(self.size)
     }
 }
 
@@ -171,23 +321,23 @@ impl<'a> Iterator for BoardIterator {
 	fn next(&mut self) -> Option<Self::Item> {
 		self.index += 1;
 
-		if self.index >= 362 {
+		if self.index > self.size * self.size {
 			return None;			
 		}
 
-		return Some(Position::from_u64(self.index - 1));
+		return Some(Position::from_u64(self.index - 1, self.size));
 	}
 }
 
 impl fmt::Display for Board {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		write!(f, "  ")?;
-		for i in 0..19 {
+		for i in 0..self.size {
 			write!(f, "{:3}", i)?;
 		}
-		for i in 0..19*19 {
-			if i % 19 == 0 {
-				write!(f, "\n{}:", ((i as f64 / 19.0).floor() as u8 + 65) as char )?;
+		for i in 0..self.size * self.size {
+			if i % self.size == 0 {
+				write!(f, "\n{}:", ((i / self.size) as u8 + 65) as char )?;
 			}
 			write!(f, " {} ", self.data[i])?;
 		}
@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::piece::{Piece, PieceWrap};
+
+// How the players' time is handed out.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TimeControl {
+	// `increment_ms` is added after every move
+	Fischer { initial_ms: u64, increment_ms: u64 },
+	// up to `delay_ms` of every move are given back
+	Bronstein { initial_ms: u64, delay_ms: u64 },
+	// every move gets `move_ms`, unused time is lost
+	PerMove { move_ms: u64 },
+}
+
+// Moves a search budget expects to still have to play out of the remaining time.
+const MOVES_TO_GO: u32 = 20;
+// Kept back from every search for move generation and I/O.
+const TIME_MARGIN: Duration = Duration::from_millis(50);
+
+// Time a search may use with `remaining` on the clock and `bonus` coming back after the move.
+pub fn search_budget(remaining: Duration, bonus: Duration) -> Duration {
+	let budget = remaining / MOVES_TO_GO + bonus * 3 / 4;
+
+	budget.min(remaining.saturating_sub(TIME_MARGIN)).max(Duration::from_millis(1))
+}
+
+// The clocks of both players. Only the clock of the side to move runs.
+#[derive(Clone, Debug)]
+pub struct Clock {
+	pub control: TimeControl,
+	remaining: [Duration; 2],
+	// side whose clock runs, and since when
+	running: Option<(Piece, Instant)>,
+	flagged: Option<Piece>,
+}
+
+#[derive(Serialize)]
+pub struct ClockSnapshot {
+	pub control: TimeControl,
+	pub remaining_ms: [u64; 2],
+	pub running: Option<Piece>,
+	pub flagged: Option<Piece>,
+}
+
+impl Clock {
+	pub fn new(control: TimeControl) -> Clock {
+		let initial = Duration::from_millis(match control {
+			TimeControl::Fischer { initial_ms, .. } => initial_ms,
+			TimeControl::Bronstein { initial_ms, .. } => initial_ms,
+			TimeControl::PerMove { move_ms } => move_ms,
+		});
+
+		Clock {
+			control,
+			remaining: [initial; 2],
+			running: None,
+			flagged: None,
+		}
+	}
+
+	// Runs `player`'s clock from `now` on and stops the other one, without charging either.
+	pub fn start(&mut self, player: Piece, now: Instant) {
+		if self.flagged.is_none() {
+			self.running = Some((player, now));
+		}
+	}
+
+	pub fn remaining(&self, player: Piece, now: Instant) -> Duration {
+		match self.running {
+			Some((running, since)) if running == player => self.remaining[player as usize].saturating_sub(now - since),
+			_ => self.remaining[player as usize],
+		}
+	}
+
+	// The player whose time has run out, if any.
+	pub fn flagged(&self, now: Instant) -> Option<Piece> {
+		self.flagged.or_else(|| match self.running {
+			Some((player, _)) if self.remaining(player, now).is_zero() => Some(player),
+			_ => None,
+		})
+	}
+
+	// Stops both clocks for good once the running one has run out at `now`, so the
+	// time can not come back through `start`. Returns the flagged player, if any.
+	pub fn check_flag(&mut self, now: Instant) -> Option<Piece> {
+		if let Some(flagged) = self.flagged(now).filter(|_| self.flagged.is_none()) {
+			self.remaining[flagged as usize] = Duration::ZERO;
+			self.flagged = Some(flagged);
+			self.running = None;
+		}
+
+		self.flagged
+	}
+
+	// Ends `player`'s move at `now` and starts the opponent's clock. Returns false,
+	// and stops both clocks, when `player` ran out of time before moving.
+	pub fn press(&mut self, player: Piece, now: Instant) -> bool {
+		let elapsed = match self.running {
+			Some((running, since)) if running == player => now - since,
+			_ => Duration::ZERO,
+		};
+		let remaining = &mut self.remaining[player as usize];
+
+		if self.flagged.is_some() || elapsed > *remaining {
+			if self.flagged.is_none() {
+				self.flagged = Some(player);
+				*remaining = Duration::ZERO;
+			}
+			self.running = None;
+			return false;
+		}
+
+		*remaining = match self.control {
+			TimeControl::Fischer { increment_ms, .. } => *remaining - elapsed + Duration::from_millis(increment_ms),
+			TimeControl::Bronstein { delay_ms, .. } => *remaining - elapsed.saturating_sub(Duration::from_millis(delay_ms)),
+			TimeControl::PerMove { move_ms } => Duration::from_millis(move_ms),
+		};
+
+		self.running = Some((player.get_opposite(), now));
+		true
+	}
+
+	// Time the engine may think for `player`'s next move.
+	pub fn search_budget(&self, player: Piece, now: Instant) -> Duration {
+		let remaining = self.remaining(player, now);
+
+		match self.control {
+			TimeControl::Fischer { increment_ms, .. } => search_budget(remaining, Duration::from_millis(increment_ms)),
+			TimeControl::Bronstein { delay_ms, .. } => search_budget(remaining, Duration::from_millis(delay_ms)),
+			// the whole move time is lost anyway when it is not used
+			TimeControl::PerMove { .. } => remaining.saturating_sub(TIME_MARGIN).max(Duration::from_millis(1)),
+		}
+	}
+
+	pub fn snapshot(&self, now: Instant) -> ClockSnapshot {
+		ClockSnapshot {
+			control: self.control,
+			remaining_ms: [Piece::Max, Piece::Min].map(|p| self.remaining(p, now).as_millis() as u64),
+			running: self.running.map(|(player, _)| player),
+			flagged: self.flagged(now),
+		}
+	}
+}
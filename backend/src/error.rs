@@ -0,0 +1,73 @@
+use std::fmt;
+
+use crate::position::Position;
+
+// Failures caused by a request, reported to the client with `code` instead of panicking.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GomokuError {
+	InvalidJson(String),
+	UnknownSubject(String),
+	InvalidBoard(String),
+	OutOfRange(Position),
+	Occupied(Position),
+	// not allowed by the rule set, e.g. a double free three
+	ForbiddenMove(Position),
+	// an opening decision asked for outside of its phase
+	InvalidOpening(String),
+	GameNotFound(String),
+	// the client's board, captures or side to move differ from the server's game
+	Desynchronized(String),
+	// a saved game that cannot be read or replayed
+	InvalidNotation(String),
+	GameOver,
+	NothingToUndo,
+	NothingToRedo,
+}
+
+impl GomokuError {
+	pub fn code(&self) -> &'static str {
+		match self {
+			GomokuError::InvalidJson(_) => "invalid_json",
+			GomokuError::UnknownSubject(_) => "unknown_subject",
+			GomokuError::InvalidBoard(_) => "invalid_board",
+			GomokuError::OutOfRange(_) => "out_of_range",
+			GomokuError::Occupied(_) => "occupied",
+			GomokuError::ForbiddenMove(_) => "forbidden_move",
+			GomokuError::InvalidOpening(_) => "invalid_opening",
+			GomokuError::GameNotFound(_) => "game_not_found",
+			GomokuError::Desynchronized(_) => "desynchronized",
+			GomokuError::InvalidNotation(_) => "invalid_notation",
+			GomokuError::GameOver => "game_over",
+			GomokuError::NothingToUndo => "nothing_to_undo",
+			GomokuError::NothingToRedo => "nothing_to_redo",
+		}
+	}
+}
+
+impl fmt::Display for GomokuError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			GomokuError::InvalidJson(e) => write!(f, "invalid json: {}", e),
+			GomokuError::UnknownSubject(subject) => write!(f, "unknown subject: {}", subject),
+			GomokuError::InvalidBoard(e) => write!(f, "invalid board: {}", e),
+			GomokuError::OutOfRange(pos) => write!(f, "position ({}, {}) is outside the board", pos.x, pos.y),
+			GomokuError::Occupied(pos) => write!(f, "{} is already occupied", pos),
+			GomokuError::ForbiddenMove(pos) => write!(f, "{} is a forbidden move", pos),
+			GomokuError::InvalidOpening(e) => write!(f, "invalid opening: {}", e),
+			GomokuError::GameNotFound(id) => write!(f, "no game with id {}", id),
+			GomokuError::Desynchronized(e) => write!(f, "out of sync with the server: {}", e),
+			GomokuError::InvalidNotation(e) => write!(f, "invalid notation: {}", e),
+			GomokuError::GameOver => write!(f, "the game is over"),
+			GomokuError::NothingToUndo => write!(f, "no move to undo"),
+			GomokuError::NothingToRedo => write!(f, "no move to redo"),
+		}
+	}
+}
+
+impl std::error::Error for GomokuError {}
+
+impl From<serde_json::Error> for GomokuError {
+	fn from(e: serde_json::Error) -> Self {
+		GomokuError::InvalidJson(e.to_string())
+	}
+}
@@ -0,0 +1,205 @@
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::{board::{Board, MoveUndo}, clock::{Clock, ClockSnapshot, TimeControl}, error::GomokuError, heuristic::{EvalWeights, Heuristic}, minimax::GomokuSolver, opening::OpeningRule, piece::{Piece, PieceWrap}, position::Position, ruleset::{self, RuleKind}};
+
+// A game played move by move, with everything needed to take moves back and
+// replay them. Black (`Piece::Max`) moves first.
+#[derive(Clone)]
+pub struct Game {
+	pub board: Board,
+	pub captures: [usize; 2],
+	// side to move
+	pub player: Piece,
+	pub rules: RuleKind,
+	pub opening: OpeningRule,
+	// pairs that win instead of the rule set's default, 0 for no capture win
+	pub capture_win: Option<usize>,
+	// `None` for untimed games
+	pub clock: Option<Clock>,
+	pub weights: EvalWeights,
+	// played moves, oldest first
+	history: Vec<MoveUndo>,
+	// moves taken back by `undo`, the next one to replay last
+	undone: Vec<MoveUndo>,
+}
+
+// What clients get to see of a game.
+#[derive(Serialize)]
+pub struct GameSnapshot<'a> {
+	pub board: &'a Board,
+	pub captures: [usize; 2],
+	pub player: Piece,
+	pub rules: RuleKind,
+	pub opening: OpeningRule,
+	pub capture_win: Option<usize>,
+	pub moves: &'a [MoveUndo],
+	pub redo_count: usize,
+	pub winner: Option<Piece>,
+	pub clock: Option<ClockSnapshot>,
+}
+
+impl Game {
+	pub fn new(size: usize, rules: RuleKind, opening: OpeningRule, capture_win: Option<usize>) -> Result<Game, GomokuError> {
+		Board::check_size(size)?;
+
+		Ok(Game {
+			board: Board::with_size(size),
+			captures: [0, 0],
+			player: Piece::Max,
+			rules,
+			opening,
+			capture_win,
+			clock: None,
+			weights: EvalWeights::default(),
+			history: Vec::new(),
+			undone: Vec::new(),
+		})
+	}
+
+	pub fn moves(&self) -> &[MoveUndo] {
+		&self.history
+	}
+
+	pub fn redo_count(&self) -> usize {
+		self.undone.len()
+	}
+
+	// The pairs that win this game, `None` when captures never win.
+	pub fn capture_win(&self) -> Option<usize> {
+		ruleset::capture_win(self.rules.ruleset(), self.capture_win)
+	}
+
+	pub fn heuristic(&self) -> Heuristic {
+		let mut heuristic = Heuristic::with_weights(&self.board, &self.captures, self.rules.ruleset(), self.weights);
+
+		heuristic.opening = self.opening;
+		heuristic.capture_win = self.capture_win();
+		heuristic
+	}
+
+	// Plays the game on `control` from now on, starting with the clock of the side to move.
+	pub fn set_clock(&mut self, control: TimeControl) {
+		let mut clock = Clock::new(control);
+
+		clock.start(self.player, Instant::now());
+		self.clock = Some(clock);
+	}
+
+	// Records a flag fall since the last request, the clock only notices it when asked.
+	pub fn check_clock(&mut self) {
+		if let Some(clock) = &mut self.clock {
+			clock.check_flag(Instant::now());
+		}
+	}
+
+	// A player who ran out of time loses.
+	pub fn winner(&self) -> Option<Piece> {
+		if let Some(flagged) = self.clock.as_ref().and_then(|clock| clock.flagged(Instant::now())) {
+			return Some(flagged.get_opposite());
+		}
+
+		self.heuristic().winner(self.player)
+	}
+
+	// A search for the side to move.
+	pub fn solver(&self, depth: usize) -> GomokuSolver {
+		let mut solver = GomokuSolver::new(self.board.clone(), self.captures, self.player, depth);
+
+		solver.is_hint = Some(true);
+		solver.rules = self.rules.ruleset();
+		solver.opening = self.opening;
+		solver.capture_win = self.capture_win;
+		solver.weights = self.weights;
+		solver.max_time = self.clock.as_ref().map(|clock| clock.search_budget(self.player, Instant::now()));
+		solver
+	}
+
+	// Plays `pos` for the side to move. Moves taken back can no longer be replayed afterwards.
+	pub fn play(&mut self, pos: Position) -> Result<&MoveUndo, GomokuError> {
+		if self.winner().is_some() {
+			return Err(GomokuError::GameOver);
+		}
+
+		let pos = self.heuristic().check_move(pos, self.player)?;
+
+		if self.clock.as_mut().is_some_and(|clock| !clock.press(self.player, Instant::now())) {
+			return Err(GomokuError::GameOver);
+		}
+
+		self.undone.clear();
+		self.apply(pos);
+
+		Ok(self.history.last().unwrap())
+	}
+
+	fn apply(&mut self, pos: Position) {
+		let capture_map = self.rules.ruleset().capture_map(&self.board, pos, self.player);
+		let undo = self.board.make_move(pos, self.player, Some(capture_map));
+
+		self.captures[self.player as usize] += undo.capture_count();
+		self.player = self.player.get_opposite();
+		self.history.push(undo);
+	}
+
+	pub fn undo(&mut self) -> Result<&MoveUndo, GomokuError> {
+		let undo = self.history.pop().ok_or(GomokuError::NothingToUndo)?;
+
+		self.board.unmake_move(&undo);
+		self.captures[undo.player as usize] -= undo.capture_count();
+		self.player = undo.player;
+		self.undone.push(undo);
+		self.restart_clock();
+
+		Ok(self.undone.last().unwrap())
+	}
+
+	pub fn redo(&mut self) -> Result<&MoveUndo, GomokuError> {
+		let undo = self.undone.pop().ok_or(GomokuError::NothingToRedo)?;
+
+		self.apply(undo.position);
+		self.restart_clock();
+
+		Ok(self.history.last().unwrap())
+	}
+
+	// Taking moves back or replaying them hands the clock to the side to move, time is not given back.
+	fn restart_clock(&mut self) {
+		if let Some(clock) = &mut self.clock {
+			clock.start(self.player, Instant::now());
+		}
+	}
+
+	// Fails when a client's view of the game no longer matches the server's.
+	pub fn check_sync(&self, board: Option<&Board>, captures: Option<[usize; 2]>, player: Option<Piece>) -> Result<(), GomokuError> {
+		if let Some(board) = board {
+			if board.size() != self.board.size() || !Board::get_diff(board, &self.board).is_empty() {
+				return Err(GomokuError::Desynchronized("board differs".to_string()));
+			}
+		}
+		if captures.is_some_and(|captures| captures != self.captures) {
+			return Err(GomokuError::Desynchronized(format!("captures are {:?}", self.captures)));
+		}
+		if player.is_some_and(|player| player != self.player) {
+			return Err(GomokuError::Desynchronized(format!("{:?} is to move", self.player)));
+		}
+
+		Ok(())
+	}
+
+	pub fn snapshot(&self) -> GameSnapshot<'_> {
+		GameSnapshot {
+			board: &self.board,
+			captures: self.captures,
+			player: self.player,
+			rules: self.rules,
+			opening: self.opening,
+			capture_win: self.capture_win(),
+			moves: &self.history,
+			redo_count: self.undone.len(),
+			winner: self.winner(),
+			clock: self.clock.as_ref().map(|clock| clock.snapshot(Instant::now())),
+		}
+	}
+}
@@ -1,8 +1,9 @@
 use std::{cmp::Ordering, f32::INFINITY};
 
 use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
 
-use crate::{board::Board, minimax::GameState, piece::{Piece, PieceWrap}, position::Position};
+use crate::{board::{Board, MoveUndo}, error::GomokuError, minimax::GameState, opening::OpeningRule, piece::{Piece, PieceWrap}, position::Position, ruleset::Ruleset};
 
 const DIRECTIONS: [[[i32; 2]; 2]; 4] = [
 	[[-1, 0], [1, 0]], //x
@@ -11,40 +12,44 @@ const DIRECTIONS: [[[i32; 2]; 2]; 4] = [
 	[[-1, 1], [1, -1]], //trbl
 ];
 
-const CAPTURE_SCORES: [f32; 6] = [
-	0.0,
-	8.0,
-	16.0,
-	32.0,
-	64.0,
-	INFINITY
-];
+// The scores the evaluation is built from. `Default` gives the built-in ones, a file
+// or a request may override any of them.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvalWeights {
+	// Indexed by how close the captured pairs are to the capture win, the last entry
+	// being one pair away. Reaching the capture win scores INFINITY instead.
+	pub captures: [f32; 5],
+	// Lines indexed by length, with no, one and both ends blocked. Fives always win.
+	pub open: [f32; 5],
+	pub half_open: [f32; 5],
+	pub closed: [f32; 5],
+	// factor of every stone's `get_position_score`
+	pub centrality: f32,
+	// factor of `get_position_score` when ordering moves
+	pub move_centrality: f32,
+}
 
-const B0_SCORES: [f32; 6] = [
-	1.0,
-	2.0,
-	4.0,
-	16.0,
-	64.0,
-	INFINITY
-];
+impl Default for EvalWeights {
+	fn default() -> Self {
+		EvalWeights {
+			captures: [0.0, 8.0, 16.0, 32.0, 64.0],
+			open: [1.0, 2.0, 4.0, 16.0, 64.0],
+			// a blocked two can be captured
+			half_open: [1.0, 2.0, 0.0, 8.0, 16.0],
+			closed: [0.0; 5],
+			centrality: 1.0,
+			move_centrality: 0.25,
+		}
+	}
+}
 
-const B1_SCORES: [f32; 6] = [
-	1.0,
-	2.0,
-	0.0, // can capture
-	8.0,
-	16.0,
-	INFINITY
-];
+// A five the opponent can still break with a capture, worth more than any four.
+const BREAKABLE_FIVE_SCORE: f32 = 128.0;
 
-const B2_SCORES: [f32; 6] = [
-	0.0,
-	0.0,
-	0.0,
-	0.0,
-	0.0,
-	INFINITY
+const CAPTURE_DIRECTIONS: [[i32; 2]; 8] = [
+	[-1, 0], [1, 0], [0, -1], [0, 1],
+	[-1, -1], [1, 1], [-1, 1], [1, -1],
 ];
 struct LineResult {
 	end: Position,
@@ -69,7 +74,7 @@ fn is_point_on_line(start: Position, end: Position, check: Position) -> bool {
 }
 
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct Line {
 	pub id: usize,
 	pub start: Position,
@@ -81,78 +86,242 @@ pub struct Line {
 	pub player: Piece,
 }
 
+// A term of `Heuristic::explain`: a line and what it adds to the score.
+#[derive(Serialize)]
+pub struct LineTerm {
+	#[serde(flatten)]
+	pub line: Line,
+	pub contribution: f32,
+}
+
+// A gapped shape found by `get_gapped_score`, from its first stone.
+#[derive(Serialize)]
+pub struct GappedTerm {
+	pub start: Position,
+	pub direction: u8,
+	pub player: Piece,
+	pub contribution: f32,
+}
+
+// How `evaluate_virtual_move` scores a move, with the change of every direction.
+#[derive(Serialize)]
+pub struct MoveTerms {
+	pub position: Position,
+	pub player: Piece,
+	// the board score after the move, as move ordering sees it
+	pub score: f32,
+	// indexed like `DIRECTIONS`: x, y, top left to bottom right, top right to bottom left
+	pub directions: [f32; 4],
+	// what `get_moves` adds for the distance to the centre
+	pub centrality: f32,
+	pub capture_map: u8,
+	pub capture_count: usize,
+}
+
+// The board score split into the terms `get_heuristic` adds up. `captures` and
+// `centrality` are per player, black first, white's are subtracted.
+#[derive(Serialize)]
+pub struct Explanation {
+	pub score: f32,
+	pub lines: Vec<LineTerm>,
+	pub gapped: Vec<GappedTerm>,
+	pub captures: [f32; 2],
+	pub centrality: [f32; 2],
+	#[serde(rename = "move")]
+	pub candidate: Option<MoveTerms>,
+}
+
+// What line scores depend on besides the line itself.
+#[derive(Clone, Copy)]
+pub struct LineScoring<'a> {
+	pub rules: &'a dyn Ruleset,
+	pub weights: &'a EvalWeights,
+}
+
 impl Line {
-	pub fn new(idx: usize, player: Piece, blocks: u8, start: Position, end: Position, direction: u8, length: usize) -> Line {
+	// `ends` are the first and the last stone of the line.
+	pub fn new(idx: usize, player: Piece, blocks: u8, ends: [Position; 2], direction: u8, length: usize, scoring: LineScoring) -> Line {
 		Line {
 			id: idx,
-			start: start,
-			end: end,
+			start: ends[0],
+			end: ends[1],
 			player: player,
 			block_pos: blocks,
 			direction: direction,
 			length: length,
-			score: Self::calculate(blocks, length, player),
+			score: Self::calculate(blocks, length, player, scoring),
 		}
 	}
 
-	pub fn calculate(blocks: u8, length: usize, player: Piece) -> f32 {
-		let val = match blocks {
-			0 => B0_SCORES[length.min(5)],
-			1 => B1_SCORES[length.min(5)],
-			2 => B1_SCORES[length.min(5)],
-			3 => B2_SCORES[length.min(5)],
-			_ => INFINITY
+	pub fn calculate(blocks: u8, length: usize, player: Piece, scoring: LineScoring) -> f32 {
+		// An overline that does not win can never become a five either.
+		if length > 5 && !scoring.rules.is_winning_length(length, player) {
+			return 0.0;
+		}
+
+		let scores = match blocks {
+			0 => &scoring.weights.open,
+			1 => &scoring.weights.half_open,
+			2 => &scoring.weights.half_open,
+			3 => &scoring.weights.closed,
+			_ => return INFINITY
 		};
+		let val = if length >= 5 {INFINITY} else {scores[length]};
 
 		val * if player == Piece::Max {1.0} else {-1.0}
 	}
 }
 
 #[derive(Clone)]
-pub struct Heuristic<'a> {
-	pub board: &'a Board,
-	pub captures: &'a [usize; 2],
+pub struct Heuristic {
+	pub board: Board,
+	pub captures: [usize; 2],
 	pub lines: FxHashMap<usize, Line>,
 	pub lines_idx: usize,
 	pub line_pos: FxHashMap<Position, [usize; 4]>,
-	pub score: Option<f32>
+	// scores of the gapped shapes by first stone and direction, built with the first score
+	pub gapped: Option<FxHashMap<(Position, u8), f32>>,
+	pub score: Option<f32>,
+	pub rules: &'static dyn Ruleset,
+	pub opening: OpeningRule,
+	// pairs that win the game, `None` when captures never win
+	pub capture_win: Option<usize>,
+	// fixed at construction, the line scores are computed with them
+	weights: EvalWeights,
+	line_pos_log: Vec<(Position, usize, usize)>,
 }
 
-impl Heuristic<'_> {
+// Everything `Heuristic::make_move` changed, so `unmake_move` can restore it.
+pub struct HeuristicUndo {
+	board: MoveUndo,
+	captures: [usize; 2],
+	lines_idx: usize,
+	score: Option<f32>,
+	removed_lines: Vec<Line>,
+	line_pos_log: Vec<(Position, usize, usize)>,
+	// whether the gapped shapes were tracked, and what they held before the move
+	gapped_built: bool,
+	gapped_log: Vec<((Position, u8), Option<f32>)>,
+}
+
+impl HeuristicUndo {
+	pub fn capture_count(&self) -> usize {
+		self.board.capture_count()
+	}
+}
+
+impl Heuristic {
 	pub fn from_game_state(state: &GameState) -> Heuristic {
+		let mut h = Self::with_weights(&state.board, &state.captures, state.rules, state.weights);
+		h.opening = state.opening;
+		h.capture_win = state.capture_win;
+		h
+	}
+
+	pub fn from_board(board: &Board, captures: &[usize; 2], rules: &'static dyn Ruleset) -> Heuristic {
+		Self::with_weights(board, captures, rules, EvalWeights::default())
+	}
+
+	pub fn with_weights(board: &Board, captures: &[usize; 2], rules: &'static dyn Ruleset, weights: EvalWeights) -> Heuristic {
+
 		let mut h = Heuristic {
 			lines_idx: 1,
-			board: &state.board,
-			captures: &state.captures,
+			board: board.clone(),
+			captures: *captures,
 			lines: FxHashMap::default(),
 			line_pos: FxHashMap::default(),
+			gapped: None,
 			score: None,
+			rules,
+			opening: OpeningRule::default(),
+			capture_win: rules.capture_win(),
+			weights,
+			line_pos_log: Vec::new(),
 		};
 
 		for pos in h.board.into_iter() {
 			if h.board[&pos].is_piece() {
 				for (i, direction) in DIRECTIONS.iter().enumerate() {
+					// the line was built from an earlier stone on it
+					if h.line_pos.get(&pos).is_some_and(|ids| ids[i] != 0) {
+						continue;
+					}
+
 					h.evaluate_position(pos, direction, i);
 				}
 			}
 		}
 
+		h.line_pos_log.clear();
+
 		h
 	}
 
-	pub fn from_new_state<'a>(&'a self, state: &'a GameState) -> Heuristic {
+	pub fn make_move(&mut self, pos: Position, player: Piece, capture_map: Option<u8>) -> HeuristicUndo {
+		let capture_map = if self.rules.captures() {capture_map} else {Some(0)};
+		let board_undo = self.board.make_move(pos, player, capture_map);
 
-		let mut n = Heuristic {
+		let mut undo = HeuristicUndo {
+			captures: self.captures,
 			lines_idx: self.lines_idx,
-			board: &state.board,
-			captures: &state.captures,
-			lines: self.lines.clone(),
-			line_pos: self.line_pos.clone(),
-			score: None,
+			score: self.score,
+			removed_lines: Vec::new(),
+			line_pos_log: Vec::new(),
+			gapped_built: self.gapped.is_some(),
+			gapped_log: Vec::new(),
+			board: board_undo,
 		};
-	
-		let diff = Board::get_diff(n.board, &self.board);
 
+		self.captures[player as usize] += undo.board.capture_count();
+		self.score = None;
+
+		let mut diff = Vec::with_capacity(1 + undo.board.captured.len());
+		diff.push(pos);
+		diff.extend_from_slice(&undo.board.captured);
+
+		undo.removed_lines = self.update_lines(&diff);
+		undo.line_pos_log = std::mem::take(&mut self.line_pos_log);
+		undo.gapped_log = self.update_gapped(&diff);
+
+		undo
+	}
+
+	pub fn unmake_move(&mut self, undo: HeuristicUndo) {
+		for id in undo.lines_idx + 1..=self.lines_idx {
+			self.lines.remove(&id);
+		}
+
+		for line in undo.removed_lines {
+			if line.id <= undo.lines_idx {
+				self.lines.insert(line.id, line);
+			}
+		}
+
+		for (pos, direction_idx, reference_idx) in undo.line_pos_log.into_iter().rev() {
+			if let Some(p) = self.line_pos.get_mut(&pos) {
+				p[direction_idx] = reference_idx;
+			}
+		}
+
+		if !undo.gapped_built {
+			self.gapped = None;
+		} else if let Some(gapped) = &mut self.gapped {
+			for (key, score) in undo.gapped_log.into_iter().rev() {
+				match score {
+					Some(score) => gapped.insert(key, score),
+					None => gapped.remove(&key),
+				};
+			}
+		}
+
+		self.board.unmake_move(&undo.board);
+		self.captures = undo.captures;
+		self.lines_idx = undo.lines_idx;
+		self.score = undo.score;
+	}
+
+	// Re-evaluates the lines around every changed position, returning the lines that were dropped.
+	fn update_lines(&mut self, diff: &[Position]) -> Vec<Line> {
 		let mut lines_to_delete = FxHashSet::<usize>::default();
 
 		lines_to_delete.reserve(4);
@@ -168,7 +337,7 @@ impl Heuristic<'_> {
 				let mut needs_line_two_eval = true;
 
 				if cur_poses[0].relocate(direction[0][0], direction[0][1]).is_ok() {
-					if let Some(l) = n.get_line_mut(&cur_poses[0], i) {
+					if let Some(l) = self.get_line(&cur_poses[0], i) {
 						lines_to_delete.insert(l.id);
 					}
 				} else {
@@ -176,15 +345,15 @@ impl Heuristic<'_> {
 				}
 				
 				if cur_poses[1].relocate(direction[1][0], direction[1][1]).is_ok() {
-					if let Some(l) = n.get_line_mut(&cur_poses[1], i) {
+					if let Some(l) = self.get_line(&cur_poses[1], i) {
 						lines_to_delete.insert(l.id);
 					}
 				} else {
 					needs_line_two_eval = false;
 				}
 
-				if needs_line_one_eval && n.board[&cur_poses[0]].is_piece() {
-					let recalc = n.evaluate_position(cur_poses[0], direction, i);
+				if needs_line_one_eval && self.board[&cur_poses[0]].is_piece() {
+					let recalc = self.evaluate_position(cur_poses[0], direction, i);
 
 					if recalc.2.is_some() {
 						lines_to_delete.extend(recalc.2.unwrap());
@@ -194,8 +363,8 @@ impl Heuristic<'_> {
 						continue;
 					}
 				}
-				if needs_line_two_eval && n.board[&cur_poses[1]].is_piece() {
-					let recalc = n.evaluate_position(cur_poses[1], direction, i);
+				if needs_line_two_eval && self.board[&cur_poses[1]].is_piece() {
+					let recalc = self.evaluate_position(cur_poses[1], direction, i);
 				
 					if recalc.2.is_some() {
 						lines_to_delete.extend(recalc.2.unwrap());
@@ -204,38 +373,22 @@ impl Heuristic<'_> {
 			}
 		}
 
-		for line in lines_to_delete {
-			n.lines.remove(&line);
-		}
-
-		return n;
+		lines_to_delete.into_iter().filter_map(|line| self.lines.remove(&line)).collect()
+	}
+	
+	fn line_scoring(&self) -> LineScoring<'_> {
+		LineScoring { rules: self.rules, weights: &self.weights }
 	}
 
-
-	pub fn from_board<'a>(board: &'a Board, captures: &'a [usize; 2]) -> Heuristic<'a> {
-
-		let mut h = Heuristic {
-			lines_idx: 1,
-			board: &board,
-			captures: &captures,
-			lines: FxHashMap::default(),
-			line_pos: FxHashMap::default(),
-			score: None,
-		};
-
-		for pos in h.board.into_iter() {
-			if h.board[&pos].is_piece() {
-				for (i, direction) in DIRECTIONS.iter().enumerate() {
-					h.evaluate_position(pos, direction, i);
-				}
-			}
+	fn calculate_captures(&self, value: &usize) -> f32 {
+		match self.capture_win {
+			Some(win) if *value >= win => INFINITY,
+			Some(_) if *value == 0 => 0.0,
+			// the last pairs before the win take the top of the table, every pair scores at least its first entry
+			Some(win) => self.weights.captures[(self.weights.captures.len() + *value).saturating_sub(win).max(1)],
+			// without a capture win every pair only counts as material
+			None => *value as f32 * self.weights.captures[1],
 		}
-
-		h
-	}
-	
-	fn calculate_captures(value: &usize) -> f32 {
-		CAPTURE_SCORES[(*value).min(5)]
 	}
 
 	fn get_line(&self, pos: &Position, direction_idx: usize) -> Option<&Line> {
@@ -249,21 +402,10 @@ impl Heuristic<'_> {
 		return line;
 	}
 
-	fn get_line_mut(&mut self, pos: &Position, direction_idx: usize) -> Option<&mut Line> {
-
-		let lines = self.line_pos.get(pos);
-		if lines.is_none() {
-			return None;
-		} 
-		
-		let line = self.lines.get_mut(&lines?[direction_idx]);
-
-		return line;
-	}
-
 	fn get_position_score(pos: Position) -> f32 {
-		let y = 1f32 - ((9.5f32 - (pos.y as f32)).abs() / 9.5f32);
-		let x = 1f32 - ((9.5f32 - (pos.x % 19) as f32).abs() / 9.5f32);
+		let half = pos.size() as f32 / 2f32;
+		let y = 1f32 - ((half - (pos.y as f32)).abs() / half);
+		let x = 1f32 - ((half - (pos.x % pos.size()) as f32).abs() / half);
 
 		return (y + x) / 2f32;
 	}
@@ -296,6 +438,222 @@ impl Heuristic<'_> {
 		}
 	}
 
+	// The piece on `pos`, or the stone of a move not played yet.
+	fn get_cell(&self, pos: &Position, stone: Option<(Position, Piece)>) -> Piece {
+		match stone {
+			Some((at, player)) if at == *pos => player,
+			_ => self.board[pos],
+		}
+	}
+
+	// `get_line_length` with `stone` on the board.
+	fn get_virtual_run(&self, direction: [i32; 2], start: Position, player: Piece, stone: Option<(Position, Piece)>) -> LineResult {
+		let mut pos = start;
+		let mut response = LineResult { blocked: true, end: start, length: 0 };
+
+		while pos.relocate(direction[0], direction[1]).is_ok() {
+			match self.get_cell(&pos, stone) {
+				cell if cell == player => {
+					response.length += 1;
+					response.end = pos;
+				},
+				cell => {
+					response.blocked = cell.is_piece();
+					break;
+				},
+			}
+		}
+
+		response
+	}
+
+	// The gapped shape starting at `start`, as its stone count and blocked ends: two runs of
+	// `player`'s stones with a single empty cell between them, `X_XX` or `XX_XX`.
+	fn get_gapped_shape(&self, start: Position, direction_idx: usize, player: Piece, stone: Option<(Position, Piece)>) -> Option<(usize, u8)> {
+		let [back, forward] = DIRECTIONS[direction_idx];
+		let before = self.get_virtual_run(back, start, player, stone);
+
+		// only from the first stone of a run
+		if before.length != 0 {
+			return None;
+		}
+
+		let first = self.get_virtual_run(forward, start, player, stone);
+
+		if first.blocked {
+			return None;
+		}
+
+		let mut gap = first.end;
+
+		if gap.relocate(forward[0], forward[1]).is_err() {
+			return None;
+		}
+
+		let second = self.get_virtual_run(forward, gap, player, stone);
+		let stones = 1 + first.length + second.length;
+
+		if second.length == 0 || !(3..=4).contains(&stones) {
+			return None;
+		}
+
+		Some((stones, if stones == 4 {0x1} else {((before.blocked as u8) << 1) + second.blocked as u8}))
+	}
+
+	// Score of the gapped shape starting at `start` in direction `direction_idx`. Contiguous
+	// lines do not see these, the gap splits them into separate fragments. A split three scores
+	// like a contiguous one with the same blocked ends, a split four, which only has the gap
+	// left to make five, like a closed four. A run shared with the shape before it only counts
+	// there, so `XX_XX_X` is a split four alone.
+	fn get_gapped_score(&self, start: Position, direction_idx: usize, stone: Option<(Position, Piece)>) -> f32 {
+		let player = self.get_cell(&start, stone);
+
+		if !player.is_piece() {
+			return 0.0;
+		}
+
+		let Some((stones, blocks)) = self.get_gapped_shape(start, direction_idx, player, stone) else {
+			return 0.0;
+		};
+
+		let back = DIRECTIONS[direction_idx][0];
+		let mut gap = start;
+
+		if gap.relocate(back[0], back[1]).is_ok() && self.get_cell(&gap, stone).is_empty() {
+			let previous = self.get_virtual_run(back, gap, player, stone);
+
+			if previous.length != 0 && self.get_gapped_shape(previous.end, direction_idx, player, stone).is_some() {
+				return 0.0;
+			}
+		}
+
+		Line::calculate(blocks, stones, player, self.line_scoring())
+	}
+
+	// Tracks the gapped shapes of the board from now on.
+	fn build_gapped(&mut self) {
+		if self.gapped.is_some() {
+			return;
+		}
+
+		let mut gapped = FxHashMap::default();
+
+		for pos in &self.board {
+			if self.board[&pos].is_empty() {
+				continue;
+			}
+
+			for direction_idx in 0..DIRECTIONS.len() {
+				let score = self.get_gapped_score(pos, direction_idx, None);
+
+				if score != 0.0 {
+					gapped.insert((pos, direction_idx as u8), score);
+				}
+			}
+		}
+
+		self.gapped = Some(gapped);
+	}
+
+	// Re-scores the gapped shapes on the lines through every changed position, returning what they held before.
+	fn update_gapped(&mut self, diff: &[Position]) -> Vec<((Position, u8), Option<f32>)> {
+		let Some(mut gapped) = self.gapped.take() else {
+			return Vec::new();
+		};
+		let mut log = Vec::new();
+
+		for pos in diff {
+			for direction_idx in 0..DIRECTIONS.len() {
+				for cur in Self::get_gapped_window(*pos, direction_idx) {
+					let key = (cur, direction_idx as u8);
+					let score = self.get_gapped_score(cur, direction_idx, None);
+					let score = if score != 0.0 {Some(score)} else {None};
+					let previous = gapped.get(&key).copied();
+
+					if score == previous {
+						continue;
+					}
+
+					log.push((key, previous));
+
+					match score {
+						Some(score) => gapped.insert(key, score),
+						None => gapped.remove(&key),
+					};
+				}
+			}
+		}
+
+		self.gapped = Some(gapped);
+		log
+	}
+
+	// Every gapped shape on the board with its score, in board order.
+	fn get_gapped_terms(&mut self) -> Vec<GappedTerm> {
+		self.build_gapped();
+
+		let mut terms: Vec<GappedTerm> = self.gapped.as_ref().unwrap().iter()
+			.map(|((start, direction), contribution)| GappedTerm { start: *start, direction: *direction, player: self.board[start], contribution: *contribution })
+			.collect();
+
+		terms.sort_by_key(|term| (term.start.y, term.start.x, term.direction));
+		terms
+	}
+
+	// The cells whose gapped shapes a stone on `pos` can change: shapes read at most
+	// five cells to either side of their first stone.
+	fn get_gapped_window(pos: Position, direction_idx: usize) -> Vec<Position> {
+		let [back, forward] = DIRECTIONS[direction_idx];
+		let mut cur = pos;
+		let mut cells = 6;
+
+		for _ in 0..5 {
+			let mut previous = cur;
+
+			if previous.relocate(back[0], back[1]).is_err() {
+				break;
+			}
+			cur = previous;
+			cells += 1;
+		}
+
+		let mut window = Vec::with_capacity(cells);
+
+		loop {
+			window.push(cur);
+
+			if window.len() == cells || cur.relocate(forward[0], forward[1]).is_err() {
+				return window;
+			}
+		}
+	}
+
+	// How the gapped shapes of the line through `pos` change when `player` plays there.
+	fn get_virtual_gapped_delta(&self, pos: Position, direction_idx: usize, player: Piece) -> f32 {
+		let [back, forward] = DIRECTIONS[direction_idx];
+
+		// a stone two cells away from any other joins no shape and blocks none
+		let near = [back, forward].iter().any(|d| (1..=2).any(|n| {
+			let mut cur = pos;
+			cur.relocate_n(d[0], d[1], n).is_ok() && self.board[&cur].is_piece()
+		}));
+
+		if !near {
+			return 0.0;
+		}
+
+		let stone = Some((pos, player));
+
+		Self::get_gapped_window(pos, direction_idx).into_iter().map(|cur| {
+			let current = match &self.gapped {
+				Some(gapped) => gapped.get(&(cur, direction_idx as u8)).copied().unwrap_or(0.0),
+				None => self.get_gapped_score(cur, direction_idx, None),
+			};
+
+			self.get_gapped_score(cur, direction_idx, stone) - current
+		}).sum()
+	}
+
 	fn populate_line_pos(&mut self, start: &Position, end: &Position, direction: [i32; 2], direction_idx: usize, reference_idx: usize) -> FxHashSet<usize>
 	{
 		let mut pos = start.clone();
@@ -318,6 +676,7 @@ impl Heuristic<'_> {
 				overwritten_lines.insert(p[direction_idx]);
 			}
 			
+			self.line_pos_log.push((pos, direction_idx, p[direction_idx]));
 			p[direction_idx] = reference_idx;
 
 			if pos == *end || pos.relocate(direction[0], direction[1]).is_err() {
@@ -348,7 +707,7 @@ impl Heuristic<'_> {
 		self.lines_idx += 1;
 
 		self.lines.insert(self.lines_idx, 
-			Line::new(self.lines_idx, self.board[&pos], block_count, scores[0].end, scores[1].end, direction_idx as u8, length)
+			Line::new(self.lines_idx, self.board[&pos], block_count, [scores[0].end, scores[1].end], direction_idx as u8, length, self.line_scoring())
 		);
 
 		let created_line = self.lines.get(&self.lines_idx).unwrap();
@@ -367,8 +726,8 @@ impl Heuristic<'_> {
 	pub fn get_heuristic(&mut self) -> f32 {
 		let mut scores = [0.0, 0.0];
 		let capture_scores = [
-			Self::calculate_captures(&self.captures[Piece::Max as usize]), 
-			Self::calculate_captures(&self.captures[Piece::Min as usize]), 	
+			self.calculate_captures(&self.captures[Piece::Max as usize]), 
+			self.calculate_captures(&self.captures[Piece::Min as usize]), 	
 		];
 
 		if capture_scores[0].is_infinite() || capture_scores[1].is_infinite() {
@@ -378,21 +737,26 @@ impl Heuristic<'_> {
 		}
 
 		for (_idx, line) in &self.lines {
+			let score = self.get_line_score(line);
+
 			if line.player == Piece::Max {
-				scores[0] += line.score;
+				scores[0] += score;
 			} else {
-				scores[1] += line.score;
+				scores[1] += score;
 			}
 		}
 
 
 		let mut solo_scores = 0.0;
+		self.build_gapped();
+
+		let gapped_scores: f32 = self.gapped.as_ref().unwrap().values().sum();
 
-		for pos in self.board {
+		for pos in &self.board {
 			match self.board[&pos] {
-				Piece::Max => solo_scores += Self::get_position_score(pos),
-				Piece::Min => solo_scores -= Self::get_position_score(pos),
-				_ => ()
+				Piece::Max => solo_scores += Self::get_position_score(pos) * self.weights.centrality,
+				Piece::Min => solo_scores -= Self::get_position_score(pos) * self.weights.centrality,
+				_ => continue
 			}
 		}
 
@@ -400,7 +764,7 @@ impl Heuristic<'_> {
 
 		self.score = Some(
 			scores[0] + scores[1] + 
-			solo_scores + 
+			solo_scores + gapped_scores +
 			capture_scores[0] - capture_scores[1]
 		);
 
@@ -411,7 +775,143 @@ impl Heuristic<'_> {
 		return self.score.unwrap();
 	}
 
+	// What `line` adds to the score: a five that can still be broken is not a win yet.
+	fn get_line_score(&self, line: &Line) -> f32 {
+		if line.score.is_infinite() && self.is_breakable(line) {
+			return BREAKABLE_FIVE_SCORE * line.score.signum();
+		}
+
+		line.score
+	}
+
+	// The score of the board term by term, and for `candidate` how move ordering would score it.
+	pub fn explain(&mut self, candidate: Option<(Position, Piece)>) -> Result<Explanation, GomokuError> {
+		let score = self.get_heuristic();
+
+		let mut lines: Vec<LineTerm> = self.lines.values()
+			.map(|line| LineTerm { line: line.clone(), contribution: self.get_line_score(line) })
+			.collect();
+
+		lines.sort_by_key(|term| (term.line.direction, term.line.start.y, term.line.start.x));
+
+		let gapped = self.get_gapped_terms();
+		let mut centrality = [0.0, 0.0];
+
+		for pos in &self.board {
+			let player = self.board[&pos];
+
+			if player.is_piece() {
+				centrality[player as usize] += Self::get_position_score(pos) * self.weights.centrality;
+			}
+		}
+
+		let candidate = match candidate {
+			Some((pos, player)) => {
+				let pos = self.check_move(pos, player)?;
+				let (eval, directions) = self.get_virtual_move_terms(pos, player);
+
+				Some(MoveTerms {
+					position: pos,
+					player,
+					score: eval.score,
+					directions,
+					centrality: Self::get_position_score(pos) * self.weights.move_centrality,
+					capture_map: eval.capture_map,
+					capture_count: eval.capture_count,
+				})
+			},
+			None => None,
+		};
+
+		Ok(Explanation {
+			score,
+			lines,
+			gapped,
+			captures: [
+				self.calculate_captures(&self.captures[Piece::Max as usize]),
+				self.calculate_captures(&self.captures[Piece::Min as usize]),
+			],
+			centrality,
+			candidate,
+		})
+	}
+
+	// The player who has won with `to_move` about to play, if any. A breakable five
+	// only wins once its owner is to move again, after the opponent passed on breaking it.
+	pub fn winner(&self, to_move: Piece) -> Option<Piece> {
+		for player in [Piece::Max, Piece::Min] {
+			if self.calculate_captures(&self.captures[player as usize]).is_infinite() {
+				return Some(player);
+			}
+		}
+
+		self.lines.values()
+			.filter(|line| line.score.is_infinite())
+			.find(|line| line.player == to_move || !self.is_breakable(line))
+			.map(|line| line.player)
+	}
+
+	// Whether the opponent of a five's owner can answer it by capturing one of its
+	// stones, or by taking the last pair it needs to win on captures.
+	pub fn is_breakable(&self, line: &Line) -> bool {
+		if !self.rules.captures() {
+			return false;
+		}
+
+		let opponent = line.player.get_opposite();
+
+		if self.capture_win.is_some_and(|win| self.captures[opponent as usize] + 1 >= win) &&
+			self.can_capture(opponent, (&self.board).into_iter().filter(|pos| self.board[pos] == line.player)) {
+			return true;
+		}
+
+		let direction = DIRECTIONS[line.direction as usize][1];
+		let mut stones = Vec::with_capacity(line.length);
+		let mut pos = line.start;
+
+		loop {
+			stones.push(pos);
+
+			if pos == line.end || pos.relocate(direction[0], direction[1]).is_err() {
+				break;
+			}
+		}
+
+		self.can_capture(opponent, stones)
+	}
+
+	// Whether `player` has a move capturing a pair that holds one of `stones`.
+	fn can_capture(&self, player: Piece, stones: impl IntoIterator<Item = Position>) -> bool {
+		let at = |pos: Position, x: i32, y: i32| -> Option<Piece> {
+			let mut pos = pos;
+			pos.relocate(x, y).ok().map(|p| self.board[p])
+		};
+
+		for stone in stones {
+			let owner = self.board[&stone];
+
+			for [x, y] in CAPTURE_DIRECTIONS {
+				if at(stone, x, y) != Some(owner) {
+					continue;
+				}
+
+				match (at(stone, -x, -y), at(stone, 2 * x, 2 * y)) {
+					(Some(Piece::Empty), Some(p)) | (Some(p), Some(Piece::Empty)) if p == player => return true,
+					_ => (),
+				}
+			}
+		}
+
+		false
+	}
+
 	pub fn evaluate_virtual_move(&self, pos: Position, player: Piece) -> Result<EvaluationScore, &str> {
+		Ok(self.get_virtual_move_terms(pos, player).0)
+	}
+
+	// `evaluate_virtual_move`, with the score change of every direction.
+	fn get_virtual_move_terms(&self, pos: Position, player: Piece) -> (EvaluationScore, [f32; 4]) {
+		let mut directions = [0.0; 4];
 		let mut result = EvaluationScore {
 			score: self.score.unwrap(),
 			capture_map: 0u8,
@@ -435,8 +935,8 @@ impl Heuristic<'_> {
 			];
 
 			let capture_map = [
-				neighbor_lines[0].is_some_and(|x| x.player.is_opposite(&player) && neighbor_lines[0].unwrap().length == 2 && x.block_pos & 0x2 != 0),
-				neighbor_lines[1].is_some_and(|x| x.player.is_opposite(&player) && neighbor_lines[1].unwrap().length == 2 && x.block_pos & 0x1 != 0)
+				self.rules.captures() && neighbor_lines[0].is_some_and(|x| x.player.is_opposite(&player) && neighbor_lines[0].unwrap().length == 2 && x.block_pos & 0x2 != 0),
+				self.rules.captures() && neighbor_lines[1].is_some_and(|x| x.player.is_opposite(&player) && neighbor_lines[1].unwrap().length == 2 && x.block_pos & 0x1 != 0)
 			];
 
 			let _block_map = [
@@ -477,7 +977,7 @@ impl Heuristic<'_> {
 
 				blocks |= 0x2;
 
-				let new_n_score = Line::calculate(neighbor_blocks, neighbor_lines[0].unwrap().length, player.get_opposite());
+				let new_n_score = Line::calculate(neighbor_blocks, neighbor_lines[0].unwrap().length, player.get_opposite(), self.line_scoring());
 				new_calc -= neighbor_lines[0].unwrap().score;
 				new_calc += new_n_score;
 			} else if neighbor_blocks[0] == player.get_opposite() {
@@ -489,14 +989,15 @@ impl Heuristic<'_> {
 
 				blocks |= 0x1;
 
-				let new_n_score = Line::calculate(neighbor_blocks, neighbor_lines[1].unwrap().length, player.get_opposite());
+				let new_n_score = Line::calculate(neighbor_blocks, neighbor_lines[1].unwrap().length, player.get_opposite(), self.line_scoring());
 				new_calc -= neighbor_lines[1].unwrap().score;
 				new_calc += new_n_score;
 			} else if neighbor_blocks[1] == player.get_opposite() {
 				blocks |= 0x1;
 			}
 
-			new_calc += Line::calculate(blocks, length, player);
+			new_calc += Line::calculate(blocks, length, player, self.line_scoring());
+			new_calc += self.get_virtual_gapped_delta(pos, i, player);
 		
 			if capture_map[0] {
 				result.capture_map |= 1u8 << (i * 2);
@@ -507,10 +1008,11 @@ impl Heuristic<'_> {
 			}
 
 
+			directions[i] = new_calc;
 			result.score += new_calc;
 		}
 
-		return Ok(result);
+		(result, directions)
 	}
 
 	fn sort_moves(mut arr: Vec<(Position, EvaluationScore)>, player: Piece) -> Vec<(Position, EvaluationScore)> {
@@ -536,7 +1038,7 @@ impl Heuristic<'_> {
 
 		moves.reserve(50);
 
-		for pos in self.board.into_iter() {
+		for pos in (&self.board).into_iter() {
 			if self.board[&pos].is_empty() {
 				continue;
 			}
@@ -555,7 +1057,7 @@ impl Heuristic<'_> {
 
 					let mut eval = self.evaluate_virtual_move(check_pos, player).unwrap();
 
-					let pos_score = Self::get_position_score(check_pos) / 4.0;
+					let pos_score = Self::get_position_score(check_pos) * self.weights.move_centrality;
 
 					if player == Piece::Max {
 						eval.score += pos_score;
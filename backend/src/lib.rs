@@ -3,10 +3,23 @@ use  serde_json::Value;
 
 pub mod minimax;
 pub mod board;
+pub mod bitboard;
 pub mod position;
 pub mod piece;
 pub mod heuristic;
 pub mod move_calculator;
+pub mod zobrist;
+pub mod transposition;
+pub mod threat_space;
+pub mod error;
+pub mod ruleset;
+pub mod renju;
+pub mod opening;
+pub mod game;
+pub mod clock;
+pub mod notation;
+pub mod analysis;
+pub mod pbrain;
 
 #[derive(Deserialize)]
 pub struct CalculateRequest {
@@ -15,5 +28,21 @@ pub struct CalculateRequest {
 	pub in_move: Option<position::Position>,
 	pub player: piece::Piece,
 	pub captures: [usize; 2],
-	pub is_hint: Option<bool>
+	pub is_hint: Option<bool>,
+	pub max_time_ms: Option<u64>,
+	pub threat_depth: Option<usize>,
+	pub threads: Option<usize>,
+	#[serde(default)]
+	pub rules: ruleset::RuleKind,
+	#[serde(default)]
+	pub opening: opening::OpeningRule,
+	#[serde(default = "position::default_size")]
+	pub size: usize,
+	// pairs that win instead of the rule set's default, 0 for no capture win
+	pub capture_win: Option<usize>,
+	// time left on the engine's clock, turned into a search budget when `max_time_ms` is not set
+	pub time_left_ms: Option<u64>,
+	pub increment_ms: Option<u64>,
+	// evaluation weights instead of the server's
+	pub weights: Option<heuristic::EvalWeights>,
 }
\ No newline at end of file
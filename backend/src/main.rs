@@ -1,15 +1,23 @@
-use std::{net::TcpStream, thread};
-use backend::minimax::Move;
+use std::{hash::{BuildHasher, RandomState}, net::TcpStream, sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc, Mutex, OnceLock}, thread, time::{Duration, Instant}};
+use backend::minimax::{self, MoveFlat};
 use backend::piece::PieceWrap;
-use backend::position::Position;
+use backend::position::{default_size, Position};
 use backend::CalculateRequest;
+use backend::error::GomokuError;
+use backend::ruleset::{self, RuleKind};
+use backend::opening::{self, OpeningRule};
+use backend::game::Game;
+use backend::clock::TimeControl;
+use backend::notation::{Notation, Record};
+use backend::analysis::{self, MoveAnalysis};
 use serde_json::{Value};
 use websocket::sync::{Server, Writer};
 use websocket::OwnedMessage;
 use serde::{Deserialize, Serialize};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use rustc_hash::FxHashMap;
 
-use backend::{board::Board, heuristic::Heuristic, minimax::GomokuSolver, piece::Piece};
+use backend::{board::Board, heuristic::{EvalWeights, Heuristic}, minimax::GomokuSolver, piece::{check_player, Piece}};
 
 #[derive(Serialize, Deserialize)]
 pub struct WSMessage
@@ -19,10 +27,38 @@ pub struct WSMessage
 	data: serde_json::Value
 }
 
+type Sender = Arc<Mutex<Writer<TcpStream>>>;
+
+// Stop flags of the searches running on a connection, next to the request id they were
+// started with, by an id of their own so searches sharing a request id do not replace each other.
+type Searches = Arc<Mutex<FxHashMap<u64, (Option<String>, Arc<AtomicBool>)>>>;
+
 #[derive(Deserialize)]
 pub struct EvalRequest {
 	board: serde_json::Map<String, Value>,
-	player: Piece
+	player: Piece,
+	#[serde(default)]
+	rules: RuleKind,
+	#[serde(default = "default_size")]
+	size: usize,
+	capture_win: Option<usize>,
+	weights: Option<EvalWeights>,
+}
+
+#[derive(Deserialize)]
+struct ExplainRequest {
+	board: serde_json::Map<String, Value>,
+	#[serde(default)]
+	captures: [usize; 2],
+	// a candidate move to break down as well, played by `player`
+	position: Option<Position>,
+	player: Option<Piece>,
+	#[serde(default)]
+	rules: RuleKind,
+	#[serde(default = "default_size")]
+	size: usize,
+	capture_win: Option<usize>,
+	weights: Option<EvalWeights>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -30,15 +66,46 @@ struct CalculationResponse
 {
 	moves: Vec<MoveFlat>,
 	depth_hits: Vec<usize>,
+	depth: usize,
 	current_score: f32,
 	score: f32,
-	mate_in: Option<i32>
+	mate_in: Option<i32>,
+	capture_win: Option<usize>,
 }
 
 #[derive(Deserialize)]
 struct PosMoveRequest {
 	board: serde_json::Map<String, Value>,
-	player: Piece
+	player: Piece,
+	#[serde(default)]
+	rules: RuleKind,
+	#[serde(default)]
+	opening: OpeningRule,
+	#[serde(default = "default_size")]
+	size: usize,
+}
+
+#[derive(Deserialize)]
+struct OpeningRequest {
+	board: serde_json::Map<String, Value>,
+	opening: OpeningRule,
+	#[serde(default)]
+	rules: RuleKind,
+	depth: Option<usize>,
+	#[serde(default = "default_size")]
+	size: usize,
+	capture_win: Option<usize>,
+	weights: Option<EvalWeights>,
+}
+
+impl OpeningRequest {
+	fn context(&self) -> opening::OpeningContext {
+		opening::OpeningContext {
+			rules: self.rules.ruleset(),
+			capture_win: self.capture_win,
+			weights: self.weights.unwrap_or_else(server_weights),
+		}
+	}
 }
 
 #[derive(Deserialize)]
@@ -46,7 +113,15 @@ struct HotseatRequest {
 	board: serde_json::Map<String, Value>,
 	player: Piece,
 	in_move: Position,
-	captures: [usize; 2]
+	captures: [usize; 2],
+	#[serde(default)]
+	rules: RuleKind,
+	#[serde(default)]
+	opening: OpeningRule,
+	#[serde(default = "default_size")]
+	size: usize,
+	capture_win: Option<usize>,
+	weights: Option<EvalWeights>,
 }
 
 
@@ -54,7 +129,8 @@ struct HotseatRequest {
 struct HotseatResponse {
 	board: Board,
 	captures: [usize; 2],
-	score: f32
+	score: f32,
+	capture_win: Option<usize>,
 }
 
 
@@ -72,189 +148,464 @@ struct EvaluationResponse
 	board_score: f32,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct MoveFlat {
-	pub score: f32,
-	pub position: Position,
-	pub order_idx: usize,
-	pub cutoff_at: usize,
+#[derive(Serialize)]
+struct ErrorResponse
+{
+	code: &'static str,
+	description: String,
 }
 
-// json not supporting infinity. Using magic numbers
-fn resolve_infinity(val: f32) -> f32 {
-	if val.is_infinite() {
-		if val.is_sign_negative() {
-			return -1234.00;
-		}
-		else {
-			return 1234.00;
-		}
-	}
-	return val;
+#[derive(Deserialize)]
+struct NewGameRequest {
+	#[serde(default)]
+	rules: RuleKind,
+	#[serde(default)]
+	opening: OpeningRule,
+	#[serde(default = "default_size")]
+	size: usize,
+	capture_win: Option<usize>,
+	// untimed when not set
+	time_control: Option<TimeControl>,
+	weights: Option<EvalWeights>,
 }
 
-fn resolve_mate_depth(score: &f32, moves: &Vec<MoveFlat>) -> Option<i32> {
-	if score.is_finite() {
-		return None;
-	}
+// `board`, `captures` and `player` are the client's view of the game, checked against the server's when sent.
+#[derive(Deserialize)]
+struct GameRequest {
+	game_id: String,
+	position: Option<Position>,
+	board: Option<serde_json::Map<String, Value>>,
+	captures: Option<[usize; 2]>,
+	player: Option<Piece>,
+	// for `engine_move`
+	depth: Option<usize>,
+}
 
-	let len = (moves.len() as i32 - 1).max(0);
+#[derive(Deserialize)]
+struct ImportRequest {
+	notation: Notation,
+	text: String,
+	#[serde(default)]
+	rules: RuleKind,
+	#[serde(default)]
+	opening: OpeningRule,
+	// for notations without a board size
+	#[serde(default = "default_size")]
+	size: usize,
+	capture_win: Option<usize>,
+	weights: Option<EvalWeights>,
+}
 
-	if score.is_sign_positive() {
-		return Some(len);
-	}
-	return Some(-len);
+#[derive(Deserialize)]
+struct ExportRequest {
+	game_id: String,
+	notation: Notation,
 }
 
-fn get_moves(res: &Move) -> Vec<MoveFlat> {
-	let mut iter = res;
+#[derive(Serialize)]
+struct ExportResponse {
+	game_id: String,
+	notation: Notation,
+	text: String,
+}
 
-	let mut moves = Vec::<MoveFlat>::new();
+#[derive(Deserialize)]
+struct AnalyzeRequest {
+	// black's move first
+	moves: Vec<Position>,
+	#[serde(default)]
+	rules: RuleKind,
+	#[serde(default)]
+	opening: OpeningRule,
+	#[serde(default = "default_size")]
+	size: usize,
+	capture_win: Option<usize>,
+	depth: Option<usize>,
+	// per position, instead of a fixed depth
+	max_time_ms: Option<u64>,
+	weights: Option<EvalWeights>,
+}
 
-	loop {
-		if iter.child.is_none() {
-			break;
-		}
+#[derive(Serialize)]
+struct AnalyzeResponse {
+	moves: Vec<MoveAnalysis>,
+}
 
-		println!("M: {}", iter.position);
-		moves.push(MoveFlat {
-			order_idx: iter.order_idx,
-			position: iter.position,
-			score: iter.score,
-			cutoff_at: iter.cutoff_at
-		});
+#[derive(Serialize)]
+struct GameResponse<T: Serialize>
+{
+	game_id: String,
+	#[serde(flatten)]
+	state: T,
+}
 
-		if iter.child.is_some() {
-			iter = iter.child.as_ref().unwrap().as_ref();
-		} else {
-			break;
-		}
+#[derive(Serialize)]
+struct StopResponse
+{
+	stopped: bool,
+}
+
+// A game and when a request last used it.
+struct Session {
+	game: Game,
+	last_used: Instant,
+}
+
+// Games untouched for this long are dropped when the next one starts.
+const GAME_IDLE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+// Games of every connection by id, so a client can pick a game up again after reconnecting.
+// Only the client that got an id knows it, `end_game` or `GAME_IDLE_TIMEOUT` drops the game.
+fn games() -> &'static Mutex<FxHashMap<String, Session>> {
+	static GAMES: OnceLock<Mutex<FxHashMap<String, Session>>> = OnceLock::new();
+
+	GAMES.get_or_init(Mutex::default)
+}
+
+// The game `game_id` names, used as of now and with a clock that ran out meanwhile flagged.
+fn find_game<'a>(games: &'a mut FxHashMap<String, Session>, game_id: &str) -> Result<&'a mut Game, GomokuError> {
+	let session = games.get_mut(game_id).ok_or_else(|| GomokuError::GameNotFound(game_id.to_string()))?;
+
+	session.last_used = Instant::now();
+	session.game.check_clock();
+	Ok(&mut session.game)
+}
+
+// Evaluation weights of requests that do not send their own, read from the JSON file
+// named by GOMOKU_WEIGHTS at start. Missing entries keep their built-in value.
+static WEIGHTS: OnceLock<EvalWeights> = OnceLock::new();
+
+fn load_weights() -> Result<EvalWeights> {
+	let Ok(path) = std::env::var("GOMOKU_WEIGHTS") else {
+		return Ok(EvalWeights::default());
+	};
+
+	let text = std::fs::read_to_string(&path).map_err(|e| anyhow!("could not read {}: {}", path, e))?;
+
+	serde_json::from_str(&text).map_err(|e| anyhow!("invalid weights in {}: {}", path, e))
+}
+
+fn server_weights() -> EvalWeights {
+	*WEIGHTS.get_or_init(EvalWeights::default)
+}
+
+// Depth of `engine_move` searches without a clock, and the limit for timed ones.
+const ENGINE_DEPTH: usize = 4;
+const TIMED_ENGINE_DEPTH: usize = 20;
+
+// Search threads for requests that do not ask for a count, from GOMOKU_THREADS.
+fn server_threads() -> usize {
+	static THREADS: OnceLock<usize> = OnceLock::new();
+
+	*THREADS.get_or_init(|| {
+		minimax::clamp_threads(std::env::var("GOMOKU_THREADS").ok()
+			.and_then(|t| t.parse::<usize>().ok())
+			.unwrap_or(1))
+	})
+}
+
+fn send_raw(sender: &Sender, message: OwnedMessage) -> Result<()> {
+	sender.lock()
+		.map_err(|_| anyhow!("sender lock poisoned"))?
+		.send_message(&message)?;
+
+	Ok(())
+}
+
+fn send(sender: &Sender, message: WSMessage) -> Result<()> {
+	send_raw(sender, OwnedMessage::Text(serde_json::to_string(&message)?))
+}
+
+// Reports a failed request to the client. Errors that are not a `GomokuError`, as is
+// or wrapped by the solver, are either unparsable request data or a server-side failure.
+fn send_error(sender: &Sender, request_id: Option<String>, error: &anyhow::Error) {
+	let gomoku_error = error.downcast_ref::<GomokuError>().or_else(|| {
+		error.downcast_ref::<std::io::Error>()
+			.and_then(|e| e.get_ref())
+			.and_then(|e| e.downcast_ref::<GomokuError>())
+	});
+
+	let code = match gomoku_error {
+		Some(e) => e.code(),
+		None if error.is::<serde_json::Error>() => "invalid_json",
+		None => "internal",
+	};
+
+	println!("ft_gomoku: error: {} ({})", error, code);
+
+	let message = WSMessage {
+		request_id,
+		subject: "error".to_string(),
+		data: serde_json::to_value(ErrorResponse {
+			code,
+			description: error.to_string(),
+		}).unwrap_or_default()
+	};
+
+	if let Err(e) = send(sender, message) {
+		println!("ft_gomoku: error: could not send error: {}", e);
 	}
+}
+
+fn handle_message(sender: &Sender, searches: &Searches, text: &str) -> Result<()> {
+	let message: WSMessage = serde_json::from_str(text)?;
+
+	match message.subject.as_str() {
+		"calculate" => handle_calculate(sender, searches, message.request_id, message.data),
+		"stop" => handle_stop(sender, searches, message.request_id),
+		"inv_moves" => handle_pos_moves(sender, message.request_id, message.data),
+		"hotseat_move" => handle_hotseat_move(sender, message.request_id, message.data),
+		"evaluate" => handle_evaluate(sender, message.request_id, message.data),
+		"opening_propose" => handle_opening_propose(sender, message.request_id, message.data),
+		"opening_choose" => handle_opening_choose(sender, message.request_id, message.data),
+		"new_game" => handle_new_game(sender, message.request_id, message.data),
+		"play" | "undo" | "redo" | "get_state" => handle_game(sender, &message.subject, message.request_id, message.data),
+		"end_game" => handle_end_game(sender, message.request_id, message.data),
+		"engine_move" => handle_engine_move(sender, searches, message.request_id, message.data),
+		"import_game" => handle_import_game(sender, message.request_id, message.data),
+		"export_game" => handle_export_game(sender, message.request_id, message.data),
+		"explain" => handle_explain(sender, message.request_id, message.data),
+		"analyze_game" => handle_analyze_game(sender, searches, message.request_id, message.data),
+		_ => Err(GomokuError::UnknownSubject(message.subject).into())
+	}
+}
 
-	return moves;
+// json not supporting infinity. Using magic numbers
+fn resolve_infinity(val: f32) -> f32 {
+	if val.is_infinite() {
+		if val.is_sign_negative() {
+			return -1234.00;
+		}
+		else {
+			return 1234.00;
+		}
+	}
+	return val;
 }
 
-fn handle_pos_moves(sender: &mut Writer<TcpStream>, request_id: Option<String>, data: Value) -> Result<()> {
+fn handle_pos_moves(sender: &Sender, request_id: Option<String>, data: Value) -> Result<()> {
 	let request: PosMoveRequest = serde_json::from_value(data)?;
+	check_player(request.player)?;
 
-	let board = Board::from_map(&request.board);
+	let board = Board::from_map(&request.board, request.size)?;
 
-	let mut heuristic = Heuristic::from_board(&board, &[0, 0]);
+	let mut heuristic = Heuristic::from_board(&board, &[0, 0], request.rules.ruleset());
+	heuristic.opening = request.opening;
 
 	heuristic.get_heuristic();
 
 	let moves: Vec<Position> = heuristic.get_invalid_moves(request.player);
 
-	sender.send_message(&OwnedMessage::Text(
-		serde_json::to_string(&WSMessage{
-			request_id,
-			subject: "inv_moves".to_string(),
-			data: serde_json::to_value(&moves)?
-		})?
-	))?;
+	send(sender, WSMessage {
+		request_id,
+		subject: "inv_moves".to_string(),
+		data: serde_json::to_value(&moves)?
+	})?;
 
 	Result::Ok(())
 }
 
-fn handle_hotseat_move(sender: &mut Writer<TcpStream>, request_id: Option<String>, data: Value) -> Result<()> {
+fn handle_hotseat_move(sender: &Sender, request_id: Option<String>, data: Value) -> Result<()> {
 	let request: HotseatRequest = serde_json::from_value(data)?;
+	check_player(request.player)?;
 
-	let mut board = Board::from_map(&request.board);
+	let mut board = Board::from_map(&request.board, request.size)?;
 
 	let mut captures = request.captures.clone();
-	let capture_count = board.set_move(request.in_move, request.player, None);
+
+	let rules = request.rules.ruleset();
+	let capture_win = ruleset::capture_win(rules, request.capture_win);
+	let weights = request.weights.unwrap_or_else(server_weights);
+
+	let mut heuristic = Heuristic::from_board(&board, &captures, rules);
+	heuristic.opening = request.opening;
+	heuristic.capture_win = capture_win;
+	let in_move = heuristic.check_move(request.in_move, request.player)?;
+
+	let capture_map = rules.capture_map(&board, in_move, request.player);
+	let capture_count = board.set_move(in_move, request.player, Some(capture_map));
 
 	captures = [
 		if request.player == Piece::Max {captures[0] + capture_count} else {captures[0]}, 
 		if request.player == Piece::Min {captures[1] + capture_count} else {captures[1]}
 	];
 
-	let mut heuristic = Heuristic::from_board(&board, &captures);
+	heuristic = Heuristic::with_weights(&board, &captures, rules, weights);
+	heuristic.capture_win = capture_win;
 
 	let score = heuristic.get_heuristic();
 
-	sender.send_message(&OwnedMessage::Text(
-		serde_json::to_string(&WSMessage {
-			request_id,
-			subject: "hotseat_move".to_string(),
-			data: serde_json::to_value(&HotseatResponse {
-				board: board,
-				captures: captures,
-				score: resolve_infinity(score)
-			})?
+	send(sender, WSMessage {
+		request_id,
+		subject: "hotseat_move".to_string(),
+		data: serde_json::to_value(&HotseatResponse {
+			board: board,
+			captures: captures,
+			score: resolve_infinity(score),
+			capture_win,
 		})?
-	))?;
+	})?;
 
 	Result::Ok(())
 }
 
-fn handle_calculate(sender: &mut Writer<TcpStream>, request_id: Option<String>, data: Value) -> Result<()> {
+// Registers the stop flag of a search and returns the id to remove it with once the search is over.
+fn add_search(searches: &Searches, request_id: Option<String>, stop: Arc<AtomicBool>) -> Result<u64> {
+	static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+	let search_id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+	searches.lock()
+		.map_err(|_| anyhow!("search list lock poisoned"))?
+		.insert(search_id, (request_id, stop));
+
+	Ok(search_id)
+}
+
+// Starts the search on a worker so the connection keeps reading, which is what lets a `stop` through.
+fn handle_calculate(sender: &Sender, searches: &Searches, request_id: Option<String>, data: Value) -> Result<()> {
 	let request: CalculateRequest = serde_json::from_value(data)?;
 
-	let mut solver = GomokuSolver::from_request(&request);
+	let mut solver = GomokuSolver::from_request(&request)?;
+
+	if request.threads.is_none() {
+		solver.threads = server_threads();
+	}
+	if request.weights.is_none() {
+		solver.weights = server_weights();
+	}
 
-	sender.send_message(&OwnedMessage::Text(
-		serde_json::to_string(&WSMessage{
-			request_id: None,
-			subject: "boardUpdate".to_string(),
-			data: serde_json::to_value(&BoardUpdateResponse {
-				board: &solver.board,
-				captures: solver.captures
-			})?
+	let search_id = add_search(searches, request_id.clone(), solver.stop_handle())?;
+
+	let sender = sender.clone();
+	let searches = searches.clone();
+
+	thread::spawn(move || {
+		if let Err(e) = run_calculate(&sender, request_id.clone(), request, solver) {
+			send_error(&sender, request_id.clone(), &e);
+		}
+
+		if let Ok(mut searches) = searches.lock() {
+			searches.remove(&search_id);
+		}
+	});
+
+	Ok(())
+}
+
+fn run_calculate(sender: &Sender, request_id: Option<String>, request: CalculateRequest, mut solver: GomokuSolver) -> Result<()> {
+	let info_sender = sender.clone();
+	let info_request_id = request_id.clone();
+
+	solver.on_info = Some(Box::new(move |info| {
+		let mut info = info.clone();
+
+		for m in info.pv.iter_mut() {
+			m.score = resolve_infinity(m.score);
+		}
+
+		let message = WSMessage {
+			request_id: info_request_id.clone(),
+			subject: "search_info".to_string(),
+			data: serde_json::to_value(&info).unwrap_or_default()
+		};
+
+		if let Err(e) = send(&info_sender, message) {
+			println!("ft_gomoku: error: could not send search_info: {}", e);
+		}
+	}));
+
+	send(sender, WSMessage {
+		request_id: None,
+		subject: "boardUpdate".to_string(),
+		data: serde_json::to_value(&BoardUpdateResponse {
+			board: &solver.board,
+			captures: solver.captures
 		})?
-	))?;
+	})?;
 
 	let result = solver.solve()?;
 
 	let mut new_board = solver.board.clone();
 	
+	let capture_map = solver.rules.capture_map(&new_board, result.position, request.player.get_opposite());
 	let capture_count = new_board.set_move(
 		result.position,
-		request.player.get_opposite(), None);
+		request.player.get_opposite(), Some(capture_map));
 
 	let captures = [
 			if request.player.get_opposite() == Piece::Max {solver.captures[0] + capture_count} else {solver.captures[0]}, 
 			if request.player.get_opposite() == Piece::Min {solver.captures[1] + capture_count} else {solver.captures[1]}
 	];
 
-	let current_score = resolve_infinity(Heuristic::from_board(&new_board, &captures).get_heuristic());
+	let capture_win = ruleset::capture_win(solver.rules, solver.capture_win);
+	let mut heuristic = Heuristic::with_weights(&new_board, &captures, solver.rules, solver.weights);
+	heuristic.capture_win = capture_win;
+
+	let current_score = resolve_infinity(heuristic.get_heuristic());
 
-	sender.send_message(&OwnedMessage::Text(
-		serde_json::to_string(&WSMessage{
-			request_id: None,
-			subject: "boardUpdate".to_string(),
-			data: serde_json::to_value(&BoardUpdateResponse {
-				board: &new_board,
-				captures: captures
-			})?
+	send(sender, WSMessage {
+		request_id: None,
+		subject: "boardUpdate".to_string(),
+		data: serde_json::to_value(&BoardUpdateResponse {
+			board: &new_board,
+			captures: captures
 		})?
-	))?;
-
-	let moves = get_moves(&result);
-	let mate_in = resolve_mate_depth(&result.score, &moves);
-
-	sender.send_message(&OwnedMessage::Text(
-		serde_json::to_string(&WSMessage{
-			request_id,
-			subject: "calculate".to_string(),
-			data: serde_json::to_value(CalculationResponse{
-				score: resolve_infinity(result.score),
-				current_score: current_score,
-				depth_hits: solver.depth_entries,
-				moves,
-				mate_in,
-			})?
+	})?;
+
+	let moves = result.principal_variation();
+	let mate_in = minimax::resolve_mate_depth(&result, solver.depth);
+
+	send(sender, WSMessage {
+		request_id,
+		subject: "calculate".to_string(),
+		data: serde_json::to_value(CalculationResponse{
+			score: resolve_infinity(result.score),
+			current_score: current_score,
+			depth_hits: solver.depth_entries,
+			depth: solver.depth,
+			moves,
+			mate_in,
+			capture_win,
 		})?
-	))?;
+	})?;
 
 	return Result::Ok(());
 }
 
-fn handle_evaluate(sender: &mut Writer<TcpStream>, request_id: Option<String>, data: Value) -> Result<()> {
+// Stops the searches started with the same request id, which then reply with the best
+// move found so far. Without a request id every search on the connection is stopped.
+fn handle_stop(sender: &Sender, searches: &Searches, request_id: Option<String>) -> Result<()> {
+	let searches = searches.lock().map_err(|_| anyhow!("search list lock poisoned"))?;
+	let mut stopped = false;
+
+	for (id, stop) in searches.values() {
+		if request_id.is_none() || *id == request_id {
+			stop.store(true, Ordering::Relaxed);
+			stopped = true;
+		}
+	}
+
+	drop(searches);
+
+	send(sender, WSMessage {
+		request_id,
+		subject: "stop".to_string(),
+		data: serde_json::to_value(StopResponse { stopped })?
+	})?;
+
+	Result::Ok(())
+}
+
+fn handle_evaluate(sender: &Sender, request_id: Option<String>, data: Value) -> Result<()> {
 	let request: EvalRequest = serde_json::from_value(data)?;
-	let board = Board::from_map(&request.board);
+	check_player(request.player)?;
+	let board = Board::from_map(&request.board, request.size)?;
+
+	let weights = request.weights.unwrap_or_else(server_weights);
 
-	let mut heuristic = Heuristic::from_board(&board, &[0, 0]);
+	let mut heuristic = Heuristic::with_weights(&board, &[0, 0], request.rules.ruleset(), weights);
+	heuristic.capture_win = ruleset::capture_win(heuristic.rules, request.capture_win);
 
 	let board_score = heuristic.get_heuristic();
 	let mut moves = heuristic.get_moves(request.player);
@@ -265,67 +616,404 @@ fn handle_evaluate(sender: &mut Writer<TcpStream>, request_id: Option<String>, d
 
 	println!("Evaluating done");
 
-	sender.send_message(&OwnedMessage::Text(
-		serde_json::to_string(&WSMessage{
-			request_id,
-			subject: "evaluate".to_string(),
-			data: serde_json::to_value(EvaluationResponse{
-				board_score: resolve_infinity(board_score),
-				moves: moves.iter().map(|f| (f.0, (f.1.score, f.1.capture_map))).collect()
-			})?
+	send(sender, WSMessage {
+		request_id,
+		subject: "evaluate".to_string(),
+		data: serde_json::to_value(EvaluationResponse{
+			board_score: resolve_infinity(board_score),
+			moves: moves.iter().map(|f| (f.0, (f.1.score, f.1.capture_map))).collect()
+		})?
+	})?;
+
+	Result::Ok(())
+}
+
+// The board score term by term, like `evaluate` but for teaching.
+fn handle_explain(sender: &Sender, request_id: Option<String>, data: Value) -> Result<()> {
+	let request: ExplainRequest = serde_json::from_value(data)?;
+	let board = Board::from_map(&request.board, request.size)?;
+	let weights = request.weights.unwrap_or_else(server_weights);
+
+	let candidate = match (request.position, request.player) {
+		(Some(position), Some(player)) => Some((position, check_player(player)?)),
+		(Some(_), None) => return Err(GomokuError::InvalidJson("a position to explain needs a player".to_string()).into()),
+		_ => None,
+	};
+
+	let mut heuristic = Heuristic::with_weights(&board, &request.captures, request.rules.ruleset(), weights);
+	heuristic.capture_win = ruleset::capture_win(heuristic.rules, request.capture_win);
+
+	let mut explanation = heuristic.explain(candidate)?;
+
+	explanation.score = resolve_infinity(explanation.score);
+	explanation.captures = explanation.captures.map(resolve_infinity);
+
+	for term in explanation.lines.iter_mut() {
+		term.line.score = resolve_infinity(term.line.score);
+		term.contribution = resolve_infinity(term.contribution);
+	}
+
+	if let Some(candidate) = explanation.candidate.as_mut() {
+		candidate.score = resolve_infinity(candidate.score);
+		candidate.directions = candidate.directions.map(resolve_infinity);
+	}
+
+	send(sender, WSMessage {
+		request_id,
+		subject: "explain".to_string(),
+		data: serde_json::to_value(&explanation)?
+	})?;
+
+	Result::Ok(())
+}
+
+// Stones the engine opens a Swap or Swap2 game with.
+fn handle_opening_propose(sender: &Sender, request_id: Option<String>, data: Value) -> Result<()> {
+	let request: OpeningRequest = serde_json::from_value(data)?;
+	let board = Board::from_map(&request.board, request.size)?;
+
+	let stones = opening::propose(request.opening, &board, &request.context())?;
+
+	send(sender, WSMessage {
+		request_id,
+		subject: "opening_propose".to_string(),
+		data: serde_json::to_value(&stones)?
+	})?;
+
+	Result::Ok(())
+}
+
+// The engine's colour choice, or the two stones it adds under Swap2.
+fn handle_opening_choose(sender: &Sender, request_id: Option<String>, data: Value) -> Result<()> {
+	let request: OpeningRequest = serde_json::from_value(data)?;
+	let board = Board::from_map(&request.board, request.size)?;
+
+	let decision = opening::choose(
+		request.opening,
+		&board,
+		&request.context(),
+		minimax::check_depth(request.depth.unwrap_or(opening::DEFAULT_DEPTH))?
+	)?;
+
+	send(sender, WSMessage {
+		request_id,
+		subject: "opening_choose".to_string(),
+		data: serde_json::to_value(&decision)?
+	})?;
+
+	Result::Ok(())
+}
+
+fn handle_new_game(sender: &Sender, request_id: Option<String>, data: Value) -> Result<()> {
+	let request: NewGameRequest = serde_json::from_value(data)?;
+	let mut game = Game::new(request.size, request.rules, request.opening, request.capture_win)?;
+
+	game.weights = request.weights.unwrap_or_else(server_weights);
+
+	if let Some(control) = request.time_control {
+		game.set_clock(control);
+	}
+
+	send(sender, WSMessage {
+		request_id,
+		subject: "new_game".to_string(),
+		data: add_game(game)?
+	})?;
+
+	Result::Ok(())
+}
+
+// Registers `game` under a new id and returns its state for the reply. The counter keeps
+// ids unique, the random part keeps other connections from guessing them.
+fn add_game(game: Game) -> Result<Value> {
+	static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+	let count = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+	let game_id = format!("{}-{:016x}", count, RandomState::new().hash_one(count));
+
+	let response = serde_json::to_value(GameResponse { game_id: game_id.clone(), state: game.snapshot() })?;
+
+	let mut games = games().lock().map_err(|_| anyhow!("game list lock poisoned"))?;
+
+	games.retain(|_, session| session.last_used.elapsed() < GAME_IDLE_TIMEOUT);
+	games.insert(game_id, Session { game, last_used: Instant::now() });
+
+	Ok(response)
+}
+
+// Starts a game from a saved one, replaying its moves. The game goes on from its last move.
+fn handle_import_game(sender: &Sender, request_id: Option<String>, data: Value) -> Result<()> {
+	let request: ImportRequest = serde_json::from_value(data)?;
+	let record = request.notation.read(&request.text, request.size)?;
+	let mut game = record.replay(request.rules, request.opening, request.capture_win)?;
+
+	game.weights = request.weights.unwrap_or_else(server_weights);
+
+	send(sender, WSMessage {
+		request_id,
+		subject: "import_game".to_string(),
+		data: add_game(game)?
+	})?;
+
+	Result::Ok(())
+}
+
+fn handle_export_game(sender: &Sender, request_id: Option<String>, data: Value) -> Result<()> {
+	let request: ExportRequest = serde_json::from_value(data)?;
+	let record = {
+		let mut games = games().lock().map_err(|_| anyhow!("game list lock poisoned"))?;
+
+		Record::from_game(find_game(&mut games, &request.game_id)?)
+	};
+
+	send(sender, WSMessage {
+		request_id,
+		subject: "export_game".to_string(),
+		data: serde_json::to_value(ExportResponse {
+			game_id: request.game_id,
+			notation: request.notation,
+			text: request.notation.write(&record),
 		})?
-	))?;
+	})?;
 
 	Result::Ok(())
 }
 
+// `play`, `undo`, `redo` and `get_state` on a game started by `new_game`. Every one replies
+// with the game's state after the change.
+fn handle_game(sender: &Sender, subject: &str, request_id: Option<String>, data: Value) -> Result<()> {
+	let request: GameRequest = serde_json::from_value(data)?;
+	let mut games = games().lock().map_err(|_| anyhow!("game list lock poisoned"))?;
+	let game = find_game(&mut games, &request.game_id)?;
+
+	let board = match &request.board {
+		Some(board) => Some(Board::from_map(board, game.board.size())?),
+		None => None,
+	};
+
+	game.check_sync(board.as_ref(), request.captures, request.player)?;
+
+	match subject {
+		"play" => {
+			let position = request.position.ok_or_else(|| GomokuError::InvalidJson("play needs a position".to_string()))?;
+			game.play(position)?;
+		},
+		"undo" => {
+			game.undo()?;
+		},
+		"redo" => {
+			game.redo()?;
+		},
+		_ => (),
+	}
+
+	let response = serde_json::to_value(GameResponse { game_id: request.game_id, state: game.snapshot() })?;
+
+	drop(games);
+
+	send(sender, WSMessage {
+		request_id,
+		subject: subject.to_string(),
+		data: response
+	})?;
+
+	Result::Ok(())
+}
+
+// Drops a game for good and replies with its last state.
+fn handle_end_game(sender: &Sender, request_id: Option<String>, data: Value) -> Result<()> {
+	let request: GameRequest = serde_json::from_value(data)?;
+	let mut session = games().lock()
+		.map_err(|_| anyhow!("game list lock poisoned"))?
+		.remove(&request.game_id)
+		.ok_or_else(|| GomokuError::GameNotFound(request.game_id.clone()))?;
+
+	session.game.check_clock();
+
+	send(sender, WSMessage {
+		request_id,
+		subject: "end_game".to_string(),
+		data: serde_json::to_value(GameResponse { game_id: request.game_id, state: session.game.snapshot() })?
+	})?;
+
+	Result::Ok(())
+}
+
+// Lets the engine play the side to move of a game, on its clock when the game is timed.
+// The search runs on a worker like `calculate` and can be stopped the same way.
+fn handle_engine_move(sender: &Sender, searches: &Searches, request_id: Option<String>, data: Value) -> Result<()> {
+	let request: GameRequest = serde_json::from_value(data)?;
+
+	let (mut solver, moves_played) = {
+		let mut games = games().lock().map_err(|_| anyhow!("game list lock poisoned"))?;
+		let game = find_game(&mut games, &request.game_id)?;
+
+		game.check_sync(None, request.captures, request.player)?;
+
+		if game.winner().is_some() {
+			return Err(GomokuError::GameOver.into());
+		}
+
+		let depth = minimax::check_depth(request.depth.unwrap_or(if game.clock.is_some() {TIMED_ENGINE_DEPTH} else {ENGINE_DEPTH}))?;
+
+		(game.solver(depth), game.moves().len())
+	};
+
+	solver.threads = server_threads();
+
+	let search_id = add_search(searches, request_id.clone(), solver.stop_handle())?;
+
+	let sender = sender.clone();
+	let searches = searches.clone();
+
+	thread::spawn(move || {
+		let res = solver.solve().map_err(anyhow::Error::from).and_then(|result| {
+			let mut games = games().lock().map_err(|_| anyhow!("game list lock poisoned"))?;
+			let game = find_game(&mut games, &request.game_id)?;
+
+			// the game went on while the engine was thinking
+			if game.moves().len() != moves_played {
+				return Err(GomokuError::Desynchronized("the game changed during the search".to_string()).into());
+			}
+
+			game.play(result.position)?;
+
+			Ok(serde_json::to_value(GameResponse { game_id: request.game_id.clone(), state: game.snapshot() })?)
+		});
+
+		match res.and_then(|response| send(&sender, WSMessage {
+			request_id: request_id.clone(),
+			subject: "engine_move".to_string(),
+			data: response
+		})) {
+			Ok(()) => (),
+			Err(e) => send_error(&sender, request_id.clone(), &e),
+		}
+
+		if let Ok(mut searches) = searches.lock() {
+			searches.remove(&search_id);
+		}
+	});
+
+	Ok(())
+}
+
+// Rates every move of a game. Runs on a worker like `calculate`, `stop` ends the
+// analysis with the moves rated so far.
+fn handle_analyze_game(sender: &Sender, searches: &Searches, request_id: Option<String>, data: Value) -> Result<()> {
+	let request: AnalyzeRequest = serde_json::from_value(data)?;
+	let depth = minimax::check_depth(request.depth.unwrap_or(if request.max_time_ms.is_some() {TIMED_ENGINE_DEPTH} else {ENGINE_DEPTH}))?;
+	let mut game = Game::new(request.size, request.rules, request.opening, request.capture_win)?;
+	let stop = Arc::new(AtomicBool::new(false));
+
+	game.weights = request.weights.unwrap_or_else(server_weights);
+
+	let search_id = add_search(searches, request_id.clone(), stop.clone())?;
+
+	let sender = sender.clone();
+	let searches = searches.clone();
+
+	thread::spawn(move || {
+		let max_time = request.max_time_ms.map(Duration::from_millis);
+
+		let res = analysis::analyze_game(game, &request.moves, depth, max_time, &stop).map_err(anyhow::Error::from).and_then(|mut moves| {
+			for m in moves.iter_mut() {
+				m.best_score = resolve_infinity(m.best_score);
+				m.score = resolve_infinity(m.score);
+				m.static_score = resolve_infinity(m.static_score);
+				m.score_drop = resolve_infinity(m.score_drop);
+			}
+
+			send(&sender, WSMessage {
+				request_id: request_id.clone(),
+				subject: "analyze_game".to_string(),
+				data: serde_json::to_value(AnalyzeResponse { moves })?
+			})
+		});
+
+		if let Err(e) = res {
+			send_error(&sender, request_id.clone(), &e);
+		}
+
+		if let Ok(mut searches) = searches.lock() {
+			searches.remove(&search_id);
+		}
+	});
+
+	Ok(())
+}
+
 fn main() {
+	// a broken weights file stops the server before it takes requests
+	let weights = load_weights().unwrap();
+
+	WEIGHTS.get_or_init(|| weights);
+
 	let server = Server::bind("0.0.0.0:8000").unwrap();
 
 	for request in server.filter_map(Result::ok) {
 		// Spawn a new thread for each connection.
 		thread::spawn(|| {
 			if !request.protocols().contains(&"rust-websocket".to_string()) {
-				request.reject().unwrap();
+				let _ = request.reject();
 				return;
 			}
 
-			let client = request.use_protocol("rust-websocket").accept().unwrap();
+			let Ok(client) = request.use_protocol("rust-websocket").accept() else {
+				return;
+			};
 
-			let ip = client.peer_addr().unwrap();
+			let Ok(ip) = client.peer_addr() else {
+				return;
+			};
 
 			println!("Connection from {}", ip);
 
-			let (mut receiver, mut sender) = client.split().unwrap();
+			let Ok((mut receiver, sender)) = client.split() else {
+				return;
+			};
+			let sender: Sender = Arc::new(Mutex::new(sender));
+			let searches: Searches = Arc::default();
 
 			for message in receiver.incoming_messages() {
-				let message = message.unwrap();
+				let message = match message {
+					Ok(message) => message,
+					Err(e) => {
+						println!("Client {} dropped: {}", ip, e);
+						break;
+					}
+				};
 
-				match message {
+				let sent = match message {
 					OwnedMessage::Close(_) => {
-						let message = OwnedMessage::Close(None);
-						sender.send_message(&message).unwrap();
 						println!("Client {} disconnected", ip);
-						return;
-					}
-					OwnedMessage::Ping(ping) => {
-						let message = OwnedMessage::Pong(ping);
-						sender.send_message(&message).unwrap();
+						break;
 					}
+					OwnedMessage::Ping(ping) => send_raw(&sender, OwnedMessage::Pong(ping)),
 					OwnedMessage::Text(text) => {
-						let message: WSMessage = serde_json::from_str(&text).unwrap();
-
-						match message.subject.as_str() {
-							"calculate" => handle_calculate(&mut sender, message.request_id, message.data),
-							"inv_moves" => handle_pos_moves(&mut sender, message.request_id, message.data),
-							"hotseat_move" => handle_hotseat_move(&mut sender, message.request_id, message.data),
-							"evaluate" => handle_evaluate(&mut sender, message.request_id, message.data),
-							_ => panic!("ft_gomoku: error: command not found: {}", message.subject)
-						}.unwrap()
+						if let Err(e) = handle_message(&sender, &searches, &text) {
+							// The request id is still worth echoing when the rest of the message is malformed.
+							let request_id = serde_json::from_str::<Value>(&text).ok()
+								.and_then(|v| v.get("request_id")?.as_str().map(String::from));
+
+							send_error(&sender, request_id, &e);
+						}
+						Ok(())
 					}
-					_ => sender.send_message(&message).unwrap(),
+					_ => send_raw(&sender, message),
+				};
+
+				if let Err(e) = sent {
+					println!("Client {} dropped: {}", ip, e);
+					break;
 				}
 			}
+
+			for (_, stop) in searches.lock().map(|s| s.values().cloned().collect::<Vec<_>>()).unwrap_or_default() {
+				stop.store(true, Ordering::Relaxed);
+			}
+
+			let _ = send_raw(&sender, OwnedMessage::Close(None));
 		});
 	}
 }
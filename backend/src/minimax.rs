@@ -1,27 +1,28 @@
-use std::{f32::INFINITY, io::Error, usize};
-use crate::{board::Board, heuristic::{EvaluationScore, Heuristic}, piece::{Piece, PieceWrap}, position::Position, CalculateRequest};
+use serde::{Deserialize, Serialize};
+use std::{f32::INFINITY, io::Error, sync::{atomic::{AtomicBool, Ordering}, Arc}, thread, time::{Duration, Instant}, usize};
+use crate::{board::Board, clock, error::GomokuError, heuristic::{EvalWeights, EvaluationScore, Heuristic}, opening::{self, OpeningRule}, piece::{check_player, Piece, PieceWrap}, position::{Position, MAX_SIZE}, ruleset::{self, RuleKind, Ruleset}, threat_space::{ThreatKind, ThreatSolver}, transposition::{Bound, TTEntry, TranspositionTable}, zobrist, CalculateRequest};
 
 
-fn print_moveset(base_pos: &Position, base_order_idx: usize, base_score: f32, m: &Move) {
-	let mut iter = m;
-
-	print!("Start score: {}, moves: ( {} ({}) [{}, {}] = {} )", base_score, base_pos, base_order_idx, iter.captures[0], iter.captures[1] , iter.depth_score);
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MoveFlat {
+	pub score: f32,
+	pub position: Position,
+	pub order_idx: usize,
+	pub cutoff_at: usize,
+}
 
-	loop {
-		if iter.child.is_none() {
-			break;
-		}
-		print!(" -> ");
-		print!("( {} ({}) [{}, {}]", iter.position, iter.order_idx, iter.captures[0], iter.captures[1]);
-		
-		if iter.child.is_some() {
-			iter = iter.child.as_ref().unwrap().as_ref();
-			print!(" = {})", iter.depth_score);
-		}
-	}
-	println!();
+// Progress of a running search, reported to `GomokuSolver::on_info`.
+#[derive(Serialize, Clone, Debug)]
+pub struct SearchInfo {
+	pub depth: usize,
+	// nodes of the main search thread
+	pub nodes: usize,
+	pub nps: u64,
+	pub best_move: Option<Position>,
+	pub pv: Vec<MoveFlat>,
 }
 
+pub type InfoCallback = Box<dyn FnMut(&SearchInfo) + Send>;
 
 #[derive(Debug)]
 pub struct Move {
@@ -59,12 +60,48 @@ impl Move {
 		self.depth_hit = child_move.depth_hit;
 		self.child = Some(Box::new(child_move));
 	}
+
+	// The moves of the line, from this one down to the last that has a reply.
+	pub fn principal_variation(&self) -> Vec<MoveFlat> {
+		let mut iter = self;
+		let mut moves = Vec::<MoveFlat>::new();
+
+		while let Some(child) = iter.child.as_ref() {
+			moves.push(MoveFlat {
+				order_idx: iter.order_idx,
+				position: iter.position,
+				score: iter.score,
+				cutoff_at: iter.cutoff_at
+			});
+
+			iter = child.as_ref();
+		}
+
+		moves
+	}
+}
+
+// Moves until the end of the line when the score of `res`, searched to `depth`, is a forced
+// win or loss, negative when black loses. Taken from `depth_hit`, which transposition table
+// cutoffs keep, as the principal variation stops at them.
+pub fn resolve_mate_depth(res: &Move, depth: usize) -> Option<i32> {
+	if res.score.is_finite() {
+		return None;
+	}
+
+	let len = (depth.saturating_sub(res.depth_hit) as i32 - 1).max(0);
+
+	Some(if res.score.is_sign_positive() {len} else {-len})
 }
 
 pub struct GameState {
 	pub board: Board,
 	pub captures: [usize; 2],
 	pub player: Piece,
+	pub rules: &'static dyn Ruleset,
+	pub opening: OpeningRule,
+	pub capture_win: Option<usize>,
+	pub weights: EvalWeights,
 }
 
 pub struct GomokuSolver
@@ -74,23 +111,119 @@ pub struct GomokuSolver
 	pub depth: usize,
 	pub player: Piece,
 	pub depth_entries: Vec<usize>,
-	pub is_hint: Option<bool>
+	pub is_hint: Option<bool>,
+	pub max_time: Option<Duration>,
+	// attacker moves the VCF/VCT search may spend before minimax runs, disabled when `None`
+	pub threat_depth: Option<usize>,
+	pub tt: Arc<TranspositionTable>,
+	// search threads, the extra ones only fill the shared transposition table
+	pub threads: usize,
+	pub rules: &'static dyn Ruleset,
+	pub opening: OpeningRule,
+	// pairs that win instead of the rule set's default, 0 for no capture win
+	pub capture_win: Option<usize>,
+	pub weights: EvalWeights,
+	killers: Vec<[Option<Position>; 2]>,
+	history: Vec<[u32; 2]>,
+	deadline: Option<Instant>,
+	timed_out: bool,
+	// 0 for the main search, helper threads count up from 1
+	thread_id: usize,
+	stop: Arc<AtomicBool>,
+	// receives `SearchInfo` from the main search thread, at most every `INFO_INTERVAL`
+	// and after every completed iteration
+	pub on_info: Option<InfoCallback>,
+	started: Instant,
+	last_info: Instant,
+	root_pv: Vec<MoveFlat>,
+}
+
+// What killer moves and (at most) the history table add to a move's static score while ordering.
+const KILLER_BONUS: f32 = 2.0;
+const HISTORY_BONUS: f32 = 2.0;
+const INFO_INTERVAL: Duration = Duration::from_millis(250);
+// Upper limit of the per-thread noise helper threads add to move ordering.
+const HELPER_JITTER: f32 = 1.0;
+
+// Smallest step above `val`, used to build null windows for PVS.
+fn next_up(val: f32) -> f32 {
+	if val.is_nan() || val == INFINITY {
+		return val;
+	}
+	if val == 0.0 {
+		return f32::from_bits(1);
+	}
+
+	let bits = val.to_bits();
+	f32::from_bits(if val > 0.0 {bits + 1} else {bits - 1})
+}
+
+fn next_down(val: f32) -> f32 {
+	-next_up(-val)
+}
+
+// Searches need at least one ply to come up with a move.
+pub fn check_depth(depth: usize) -> Result<usize, GomokuError> {
+	if depth == 0 {
+		return Err(GomokuError::InvalidJson("depth must be at least 1".to_string()));
+	}
+
+	Ok(depth)
+}
+
+// Search threads a request may ask for, one per core at most: every thread searches a
+// copy of the position, more of them only cost memory and time slices.
+pub fn clamp_threads(threads: usize) -> usize {
+	let cores = thread::available_parallelism().map_or(1, |n| n.get());
+
+	threads.clamp(1, cores)
 }
 
 impl GomokuSolver {
-	pub fn from_request(msg: &CalculateRequest) -> GomokuSolver {
+	pub fn from_request(msg: &CalculateRequest) -> Result<GomokuSolver, GomokuError> {
+		check_depth(msg.depth)?;
+		check_player(msg.player)?;
 
 		let mut solver = GomokuSolver{
-			board: Board::from_map(&msg.board),
+			board: Board::from_map(&msg.board, msg.size)?,
 			captures: msg.captures,
 			depth_entries: vec![0; msg.depth + 1],
 			depth: msg.depth,
 			player: msg.player,
-			is_hint: msg.is_hint
+			is_hint: msg.is_hint,
+			max_time: msg.max_time_ms.map(Duration::from_millis).or(msg.time_left_ms.map(|time_left| clock::search_budget(
+				Duration::from_millis(time_left),
+				Duration::from_millis(msg.increment_ms.unwrap_or(0))
+			))),
+			threat_depth: msg.threat_depth,
+			tt: Arc::new(TranspositionTable::default()),
+			threads: clamp_threads(msg.threads.unwrap_or(1)),
+			rules: msg.rules.ruleset(),
+			opening: msg.opening,
+			capture_win: msg.capture_win,
+			weights: msg.weights.unwrap_or_default(),
+			killers: Vec::new(),
+			history: vec![[0; 2]; MAX_SIZE * MAX_SIZE],
+			deadline: None,
+			timed_out: false,
+			thread_id: 0,
+			stop: Arc::new(AtomicBool::new(false)),
+			on_info: None,
+			started: Instant::now(),
+			last_info: Instant::now(),
+			root_pv: Vec::new(),
 		};
 
 		if msg.in_move.is_some() {
-			let capture_count = solver.board.set_move(msg.in_move.unwrap(), msg.player, None);
+			let in_move = msg.in_move.unwrap();
+
+			let mut heuristic = Heuristic::from_board(&solver.board, &solver.captures, solver.rules);
+			heuristic.opening = solver.opening;
+			heuristic.capture_win = ruleset::capture_win(solver.rules, solver.capture_win);
+			let in_move = heuristic.check_move(in_move, msg.player)?;
+
+			let capture_map = solver.rules.capture_map(&solver.board, in_move, msg.player);
+			let capture_count = solver.board.set_move(in_move, msg.player, Some(capture_map));
 		
 			solver.captures = [
 				if msg.player == Piece::Max {solver.captures[0] + capture_count} else {solver.captures[0]}, 
@@ -98,78 +231,264 @@ impl GomokuSolver {
 			];
 		}
 
-		return solver;
+		return Ok(solver);
+	}
+
+	pub fn new(board: Board, captures: [usize; 2], player: Piece, depth: usize) -> GomokuSolver {
+		GomokuSolver {
+			board,
+			captures,
+			depth_entries: vec![0; depth + 1],
+			depth,
+			player,
+			is_hint: None,
+			max_time: None,
+			threat_depth: None,
+			tt: Arc::new(TranspositionTable::default()),
+			threads: 1,
+			rules: RuleKind::default().ruleset(),
+			opening: OpeningRule::default(),
+			capture_win: None,
+			weights: EvalWeights::default(),
+			killers: Vec::new(),
+			history: vec![[0; 2]; MAX_SIZE * MAX_SIZE],
+			deadline: None,
+			timed_out: false,
+			thread_id: 0,
+			stop: Arc::new(AtomicBool::new(false)),
+			on_info: None,
+			started: Instant::now(),
+			last_info: Instant::now(),
+			root_pv: Vec::new(),
+		}
+	}
+
+	fn helper(&self, thread_id: usize, stop: Arc<AtomicBool>) -> GomokuSolver {
+		GomokuSolver {
+			board: self.board.clone(),
+			captures: self.captures,
+			depth_entries: vec![0; self.depth_entries.len()],
+			depth: self.depth,
+			player: self.player,
+			is_hint: self.is_hint,
+			max_time: self.max_time,
+			threat_depth: None,
+			tt: self.tt.clone(),
+			threads: 1,
+			rules: self.rules,
+			opening: self.opening,
+			capture_win: self.capture_win,
+			weights: self.weights,
+			killers: Vec::new(),
+			history: vec![[0; 2]; MAX_SIZE * MAX_SIZE],
+			deadline: None,
+			timed_out: false,
+			thread_id,
+			stop,
+			on_info: None,
+			started: self.started,
+			last_info: self.last_info,
+			root_pv: Vec::new(),
+		}
+	}
+
+	// Flag that aborts the search from another thread once set. `solve` then
+	// returns the best move found so far.
+	pub fn stop_handle(&self) -> Arc<AtomicBool> {
+		self.stop.clone()
+	}
+
+	// Makes `stop` abort this search, so one flag can stop several searches in a row.
+	pub fn set_stop_handle(&mut self, stop: Arc<AtomicBool>) {
+		self.stop = stop;
+	}
+
+	// The deadline never cuts the first iteration of the main search short, a stop request does.
+	fn is_timed_out(&mut self) -> bool {
+		if self.timed_out {
+			return true;
+		}
+
+		self.timed_out = self.stop.load(Ordering::Relaxed) ||
+			((self.depth > 1 || self.thread_id != 0) && self.deadline.is_some_and(|d| Instant::now() >= d));
+
+		if self.on_info.is_some() && self.last_info.elapsed() >= INFO_INTERVAL {
+			self.report();
+		}
+
+		self.timed_out
 	}
 
-	fn minimax(&mut self, mut depth: usize, old_heuristic: &Heuristic, state: &GameState, mut alpha: f32, mut beta: f32) -> Move
+	fn report(&mut self) {
+		let nodes = self.depth_entries.iter().sum::<usize>();
+		let elapsed = self.started.elapsed().as_secs_f64();
+
+		let info = SearchInfo {
+			depth: self.depth,
+			nodes,
+			nps: if elapsed > 0.0 {(nodes as f64 / elapsed) as u64} else {0},
+			best_move: self.root_pv.first().map(|m| m.position),
+			pv: self.root_pv.clone(),
+		};
+
+		if let Some(on_info) = self.on_info.as_mut() {
+			on_info(&info);
+		}
+
+		self.last_info = Instant::now();
+	}
+
+	fn update_root_pv(&mut self, depth: usize, move_store: &Move) {
+		if depth == self.depth && self.thread_id == 0 && self.on_info.is_some() {
+			self.root_pv = move_store.principal_variation();
+		}
+	}
+
+	fn store_cutoff(&mut self, ply: usize, depth: usize, pos_move: &(Position, EvaluationScore), player: Piece) {
+		if pos_move.1.capture_count == 0 && self.killers[ply][0] != Some(pos_move.0) {
+			self.killers[ply][1] = self.killers[ply][0];
+			self.killers[ply][0] = Some(pos_move.0);
+		}
+
+		let entry = &mut self.history[pos_move.0.to_u64(self.board.size()) as usize][player as usize];
+		*entry = entry.saturating_add((depth * depth) as u32);
+	}
+
+	// Puts the transposition move first, then captures, and orders the rest by
+	// static score nudged by the killer slots and the history table.
+	// Helper threads each see a slightly different move order so they do not all search the same tree.
+	fn jitter(&self, pos: &Position) -> f32 {
+		if self.thread_id == 0 {
+			return 0.0;
+		}
+
+		let seed = (pos.to_u64(self.board.size()) << 8 | self.thread_id as u64).wrapping_mul(0x9E3779B97F4A7C15);
+
+		(seed >> 40) as f32 / (1u64 << 24) as f32 * HELPER_JITTER
+	}
+
+	fn order_moves(&self, moves: &mut [(Position, EvaluationScore)], player: Piece, ply: usize, tt_move: Option<Position>) {
+		let sign = if player.is_max() {1.0} else {-1.0};
+		let history_max = self.history.iter().map(|h| h[player as usize]).max().unwrap_or(0).max(1) as f32;
+
+		let priority = |m: &(Position, EvaluationScore)| -> u8 {
+			if tt_move == Some(m.0) {
+				2
+			} else if m.1.capture_count > 0 {
+				1
+			} else {
+				0
+			}
+		};
+		let gain = |m: &(Position, EvaluationScore)| -> f32 {
+			let history = self.history[m.0.to_u64(self.board.size()) as usize][player as usize] as f32;
+			let killer = if self.killers[ply].contains(&Some(m.0)) {KILLER_BONUS} else {0.0};
+
+			m.1.score * sign + killer + history / history_max * HISTORY_BONUS + self.jitter(&m.0)
+		};
+
+		moves.sort_by(|a, b| {
+			priority(b).cmp(&priority(a))
+				.then(b.1.capture_count.cmp(&a.1.capture_count))
+				.then(gain(b).total_cmp(&gain(a)))
+		});
+	}
+
+	fn minimax(&mut self, depth: usize, heuristic: &mut Heuristic, player: Piece, mut alpha: f32, mut beta: f32) -> Move
 	{
-		let mut heuristic = old_heuristic.from_new_state(&state);
+		if self.is_timed_out() {
+			return Move::make_half_empty(0.0, depth, 0.0, &heuristic.captures);
+		}
+
+		let ply = self.depth - depth;
+
+		self.depth_entries[ply] += 1;
+
+		let key = heuristic.board.hash() ^ zobrist::captures_key(&heuristic.captures) ^ zobrist::side_key(player);
+		let tt_entry = self.tt.probe(key);
+
+		if let Some(entry) = tt_entry {
+			if depth != self.depth && entry.is_usable(depth, alpha, beta) {
+				return Move::make_half_empty(entry.score, depth.saturating_sub(entry.mate_distance), entry.score, &heuristic.captures);
+			}
+		}
+
+		let (alpha_orig, beta_orig) = (alpha, beta);
+
 		let mut found_move = false;
 
-		self.depth_entries[self.depth - depth] += 1;
-		let heuristical_score = heuristic.get_heuristic();
+		let mut heuristical_score = heuristic.get_heuristic();
+
+		// A five the opponent may still break by a capture only ends the game once its owner is to move again.
+		if let Some(winner) = heuristic.winner(player) {
+			heuristical_score = if winner.is_max() {INFINITY} else {-INFINITY};
+		}
 
 		let mut move_store = Move::make_half_empty(
-			if state.player.is_max() {-INFINITY} else {INFINITY},
+			if player.is_max() {-INFINITY} else {INFINITY},
 			 depth, heuristical_score, &heuristic.captures);
 
 		if depth == 0 || heuristical_score.is_infinite() {
-			if depth != 0 && state.captures[state.player as usize] == 4 && (
-				(state.player.is_min() && heuristical_score.is_sign_positive()) ||
-				(state.player.is_max() && heuristical_score.is_sign_negative())
-			)
-			{
-				depth = 1;
-			}
-			else {
-				return Move::make_half_empty(heuristical_score, depth, heuristical_score, &heuristic.captures);
-			}
+			return Move::make_half_empty(heuristical_score, depth, heuristical_score, &heuristic.captures);
 		}
 
-		let mut possible_moves = heuristic.get_moves(state.player);
+		let mut possible_moves = Self::candidate_moves(heuristic, player);
 
-		if possible_moves.len() == 0 && state.board[&Position::new(10, 10)].is_empty() {
-			possible_moves.push((Position::new(10, 10), EvaluationScore {
-				capture_count: 0,
-				capture_map: 0,
-				score: 0.0
-			}));
-		} else if possible_moves.len() == 0 {
-			panic!("No possible starter move found")
+		// a full board ends the game in a draw
+		if possible_moves.is_empty() {
+			return Move::make_half_empty(heuristical_score, depth, heuristical_score, &heuristic.captures);
 		}
 
+		self.order_moves(&mut possible_moves, player, ply, tt_entry.and_then(|e| e.best_move));
+
 		move_store.cutoff_at = possible_moves.len();
 
 		for (i, pos_move) in possible_moves.iter().enumerate() {
-			let mut new_board = state.board.clone();
-
-			if heuristic.validate_move(pos_move.0, state.player) == false {
+			if heuristic.validate_move(pos_move.0, player) == false {
 				continue;
 			}
 
-			let capture_count = new_board.set_move(pos_move.0, state.player, Some(pos_move.1.capture_map));
+			let undo = heuristic.make_move(pos_move.0, player, Some(pos_move.1.capture_map));
 
-			let node_result = self.minimax(depth - 1, &heuristic, &GameState {
-				board: new_board,
-				captures: [
-					if state.player == Piece::Max {heuristic.captures[0] + capture_count} else {heuristic.captures[0]}, 
-					if state.player == Piece::Min {heuristic.captures[1] + capture_count} else {heuristic.captures[1]}
-				],
-				player: state.player.get_opposite(),
-			}, alpha, beta);
+			// Principal variation search: after the first move, only prove that a move is
+			// not better than the current best with a null window, and re-search on failure.
+			// The window is built on the side's own bound, the other one may stay infinite.
+			let own_bound = if player.is_max() {alpha} else {beta};
 
-			if depth == self.depth {
-				println!("RES D: {}: pos: {} PRED: {} V:{}", node_result.depth_hit, pos_move.0, pos_move.1.score, node_result.score);
-				print_moveset(&pos_move.0, i, heuristical_score, &node_result);
+			let node_result = if !found_move || own_bound.is_infinite() {
+				self.minimax(depth - 1, heuristic, player.get_opposite(), alpha, beta)
+			} else if player.is_max() {
+				let probe = self.minimax(depth - 1, heuristic, player.get_opposite(), alpha, next_up(alpha));
+
+				if probe.score > alpha && probe.score < beta && !self.timed_out {
+					self.minimax(depth - 1, heuristic, player.get_opposite(), alpha, beta)
+				} else {
+					probe
+				}
+			} else {
+				let probe = self.minimax(depth - 1, heuristic, player.get_opposite(), next_down(beta), beta);
+
+				if probe.score < beta && probe.score > alpha && !self.timed_out {
+					self.minimax(depth - 1, heuristic, player.get_opposite(), alpha, beta)
+				} else {
+					probe
+				}
+			};
+
+			heuristic.unmake_move(undo);
+
+			if self.timed_out {
+				break;
 			}
 
-			if state.player.is_max() {
+			if player.is_max() {
 				if node_result.score > move_store.score || 
 					(found_move == false) ||
 					(move_store.score == INFINITY && node_result.depth_hit > move_store.depth_hit) {
 					
 					found_move = true;
 					move_store.update(i, node_result, pos_move);
+					self.update_root_pv(depth, &move_store);
 				}
 
 				alpha = alpha.max(move_store.score);
@@ -177,6 +496,7 @@ impl GomokuSolver {
 				if move_store.score > beta || 
 					(depth != self.depth && move_store.score == INFINITY) {
 					move_store.cutoff_at = i;
+					self.store_cutoff(ply, depth, pos_move, player);
 					break;
 				}
 			} else {
@@ -185,6 +505,7 @@ impl GomokuSolver {
 					(move_store.score == -INFINITY && node_result.depth_hit > move_store.depth_hit) {
 					found_move = true;
 					move_store.update(i, node_result, pos_move);
+					self.update_root_pv(depth, &move_store);
 				}
 
 				beta = beta.min(move_store.score);
@@ -193,33 +514,223 @@ impl GomokuSolver {
 					(depth != self.depth && move_store.score == -INFINITY)
 				 {
 					move_store.cutoff_at = i;
+					self.store_cutoff(ply, depth, pos_move, player);
 					break;
 				}
 			}
 		}
+
+		if found_move && !self.timed_out {
+			self.tt.store(key, TTEntry {
+				depth,
+				bound: if move_store.score <= alpha_orig {
+					Bound::Upper
+				} else if move_store.score >= beta_orig {
+					Bound::Lower
+				} else {
+					Bound::Exact
+				},
+				score: move_store.score,
+				mate_distance: depth.saturating_sub(move_store.depth_hit),
+				best_move: Some(move_store.position),
+			});
+		}
+
 		return move_store;
 	}
 
+	// Moves next to existing stones, plus the cells away from every stone the opening
+	// rule asks for, like the first move.
+	fn candidate_moves(heuristic: &mut Heuristic, player: Piece) -> Vec<(Position, EvaluationScore)> {
+		let mut possible_moves = heuristic.get_moves(player);
+
+		for pos in opening::candidates(heuristic.opening, &heuristic.board, player) {
+			if heuristic.score.is_none() {
+				heuristic.get_heuristic();
+			}
+			if !possible_moves.iter().any(|m| m.0 == pos) {
+				let score = heuristic.evaluate_virtual_move(pos, player).unwrap();
+				possible_moves.push((pos, score));
+			}
+		}
+
+		possible_moves
+	}
+
+	// Statically best legal move, for a search stopped before its first root move finished.
+	fn fallback_move(&self, heuristic: &mut Heuristic, player: Piece) -> Move {
+		let mut possible_moves = Self::candidate_moves(heuristic, player);
+		let sign = if player.is_max() {1.0} else {-1.0};
+
+		possible_moves.sort_by(|a, b| (b.1.score * sign).total_cmp(&(a.1.score * sign)));
+
+		let pos_move = possible_moves.into_iter()
+			.find(|m| heuristic.validate_move(m.0, player))
+			.unwrap_or((opening::center(&heuristic.board), EvaluationScore { capture_count: 0, capture_map: 0, score: 0.0 }));
+
+		let undo = heuristic.make_move(pos_move.0, player, Some(pos_move.1.capture_map));
+		let score = heuristic.get_heuristic();
+		let child = Move::make_half_empty(score, 0, score, &heuristic.captures);
+		heuristic.unmake_move(undo);
+
+		let mut res = Move::make_half_empty(score, 0, heuristic.get_heuristic(), &heuristic.captures);
+		res.update(0, child, &pos_move);
+
+		res
+	}
+
+	// Runs VCF, then VCT, for the side to move and turns a found line into a move chain.
+	fn solve_threats(&self, state: &GameState, depth: usize) -> Option<Move> {
+		let mut threat_solver = ThreatSolver::new(&state.board, state.captures, state.player, state.rules);
+		threat_solver.capture_win = state.capture_win;
+		threat_solver.stop = self.stop.clone();
+		// half of the move's budget, the main search gets the rest
+		threat_solver.deadline = self.max_time.map(|t| self.started + t / 2);
+
+		let line = threat_solver.solve(ThreatKind::Vcf, depth)
+			.or_else(|| threat_solver.solve(ThreatKind::Vct, depth))?;
+
+		let score = if state.player.is_max() {INFINITY} else {-INFINITY};
+		let mut res = Move::make_half_empty(score, 0, score, &state.captures);
+
+		for pos in line.iter().rev() {
+			let mut parent = Move::make_half_empty(score, 0, score, &state.captures);
+
+			parent.position = *pos;
+			parent.child = Some(Box::new(res));
+			res = parent;
+		}
+
+		Some(res)
+	}
+
+	// Iterative deepening up to `self.depth`, returning the deepest completed iteration.
+	// With a time budget `depth` is the upper bound, otherwise only the requested depth is searched.
+	fn iterate(&mut self, heuristic: &mut Heuristic, player: Piece) -> Option<(usize, Move)> {
+		let max_depth = self.depth;
+		let mut start_depth = if self.max_time.is_some() {1} else {max_depth};
+
+		// Odd helpers run one iteration ahead of the rest.
+		if self.thread_id % 2 == 1 && start_depth < max_depth {
+			start_depth += 1;
+		}
+
+		// one budget for the whole move, threat search included
+		self.deadline = self.max_time.map(|t| self.started + t);
+		self.timed_out = false;
+		self.killers = vec![[None; 2]; max_depth + 1];
+
+		let mut best: Option<(usize, Move)> = None;
+
+		for depth in start_depth..=max_depth {
+			self.depth = depth;
+
+			let res = self.minimax(depth, heuristic, player, -INFINITY, INFINITY);
+
+			if self.timed_out {
+				// Stopped before any iteration completed: keep the best of the root moves searched so far.
+				if best.is_none() && res.child.is_some() {
+					best = Some((depth, res));
+				}
+				break;
+			}
+
+			if self.thread_id == 0 && self.on_info.is_some() {
+				self.root_pv = res.principal_variation();
+				self.report();
+			}
+
+			let is_mate = res.score.is_infinite();
+
+			best = Some((depth, res));
+
+			if is_mate {
+				break;
+			}
+		}
+
+		self.depth = max_depth;
+
+		best
+	}
+
 	pub fn solve<'a>(&mut self) -> Result<Move, Error>
 	{
-		println!("Starting minimax.. as player {}\n", if self.is_hint.is_some_and(|x| x == true) { self.player } else {self.player.get_opposite() });
+		eprintln!("Starting minimax.. as player {}\n", if self.is_hint.is_some_and(|x| x == true) { self.player } else {self.player.get_opposite() });
 
 		let game_state = GameState {
 			board: self.board.clone(),
 			captures: self.captures,
 			player: if self.is_hint.is_some_and(|x| x == true) { self.player } else {self.player.get_opposite() },
+			rules: self.rules,
+			opening: self.opening,
+			capture_win: ruleset::capture_win(self.rules, self.capture_win),
+			weights: self.weights,
 		};
 
 		let mut heuristic = Heuristic::from_game_state(&game_state);
 
-		let base_score = heuristic.get_heuristic();
+		if !game_state.board.into_iter().any(|pos| game_state.board[&pos].is_empty()) {
+			return Err(Error::other(GomokuError::GameOver));
+		}
+
+		self.started = Instant::now();
+		self.last_info = self.started;
+		self.root_pv = Vec::new();
+
+		if let Some(res) = self.threat_depth.and_then(|depth| self.solve_threats(&game_state, depth)) {
+			// the line is searched to its end
+			self.depth = res.principal_variation().len();
+
+			if self.on_info.is_some() {
+				self.root_pv = res.principal_variation();
+				self.report();
+			}
+			return Ok(res);
+		}
+
+		let stop_helpers = Arc::new(AtomicBool::new(false));
+
+		let (best, helper_results) = thread::scope(|scope| {
+			let helpers: Vec<_> = (1..self.threads).map(|thread_id| {
+				let mut helper = self.helper(thread_id, stop_helpers.clone());
+				let mut heuristic = heuristic.clone();
+				let player = game_state.player;
+
+				scope.spawn(move || {
+					let res = helper.iterate(&mut heuristic, player);
+					(res, helper.depth_entries)
+				})
+			}).collect();
 
-		let res = self.minimax(self.depth, &heuristic, &game_state, -INFINITY, INFINITY);
+			let best = self.iterate(&mut heuristic, game_state.player);
+
+			stop_helpers.store(true, Ordering::Relaxed);
+
+			(best, helpers.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>())
+		});
+
+		let mut best = best;
+
+		// A helper that finished a deeper iteration than the main search wins.
+		for (res, depth_entries) in helper_results {
+			for (total, count) in self.depth_entries.iter_mut().zip(depth_entries) {
+				*total += count;
+			}
+
+			if res.as_ref().is_some_and(|(depth, _)| best.as_ref().is_none_or(|b| *depth > b.0)) {
+				best = res;
+			}
+		}
+
+		let (depth, res) = match best {
+			Some(best) => best,
+			None => (0, self.fallback_move(&mut heuristic, game_state.player)),
+		};
 
-		println!("----");
-		print_moveset(&res.position, res.order_idx, base_score, &res);
+		self.depth = depth;
 
-		println!("SCORE: {} - depth: {:?} = {}", res.score, &self.depth_entries, &self.depth_entries.iter().sum::<usize>());
+		eprintln!("SCORE: {} - depth: {} ({:?}) = {}", res.score, depth, &self.depth_entries, &self.depth_entries.iter().sum::<usize>());
 
 		return Ok(res);
 	}
@@ -2,7 +2,7 @@
 
 
 
-use crate::{heuristic::Heuristic, piece::{Piece, PieceWrap}, position::Position};
+use crate::{error::GomokuError, heuristic::Heuristic, opening, piece::{Piece, PieceWrap}, position::Position};
 
 const DIRECTIONS: [[[i32; 2]; 2]; 4] = [
 	[[-1, 0], [1, 0]], //x
@@ -11,11 +11,11 @@ const DIRECTIONS: [[[i32; 2]; 2]; 4] = [
 	[[-1, 1], [1, -1]], //trbl
 ];
 
-impl Heuristic<'_> {
-	pub fn get_invalid_moves(&self, player: Piece) -> Vec<Position> {
+impl Heuristic {
+	pub fn get_invalid_moves(&mut self, player: Piece) -> Vec<Position> {
 		let mut positions = Vec::new();
 
-		for pos in self.board {
+		for pos in &self.board {
 			if self.board[&pos].is_empty() && self.validate_move(pos, player) == false {
 				positions.push(pos);
 			}
@@ -63,7 +63,13 @@ impl Heuristic<'_> {
 		return 0;
 	}
 
-	pub fn validate_move(&self, pos: Position, player: Piece) -> bool {
+	// Whether the rule set allows `player` to play the empty cell `pos`.
+	pub fn validate_move(&mut self, pos: Position, player: Piece) -> bool {
+		opening::is_allowed(self.opening, &self.board, pos, player) && self.rules.is_legal(self, pos, player)
+	}
+
+	// Whether the move creates two free threes at once.
+	pub fn is_double_three(&self, pos: Position, player: Piece) -> bool {
 		let mut free_three_count = 0;
 		for (i, direction) in DIRECTIONS.iter().enumerate() {
 			let results = (
@@ -78,6 +84,21 @@ impl Heuristic<'_> {
 			free_three_count += 1;
 		}
 
-		free_three_count != 2
+		free_three_count == 2
+	}
+
+	// Every reason a requested move can not be played, as an error for the client.
+	// On success, the move as a cell of this board.
+	pub fn check_move(&mut self, pos: Position, player: Piece) -> Result<Position, GomokuError> {
+		let pos = self.board.position(pos.x, pos.y).ok_or(GomokuError::OutOfRange(pos))?;
+
+		if self.board[&pos].is_piece() {
+			return Err(GomokuError::Occupied(pos));
+		}
+		if !self.validate_move(pos, player) {
+			return Err(GomokuError::ForbiddenMove(pos));
+		}
+
+		Ok(pos)
 	}
-}
\ No newline at end of file
+}
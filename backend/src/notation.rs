@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{board::Board, error::GomokuError, game::Game, opening::OpeningRule, position::{Position, DEFAULT_SIZE}, ruleset::RuleKind};
+
+// Text formats games are saved in and loaded from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Notation {
+	// Gomocup / Piskvork records: a `Piskvorky WxH` header, then one `x,y,time` line per move, 1-based
+	Psq,
+	// SGF with `GM[4]`, a main line of `B[..]` / `W[..]` nodes with letter coordinates from `a`
+	Sgf,
+	// `Position` names separated by spaces, e.g. `J9 J10 K11`
+	Moves,
+}
+
+// The moves of a game in the order they were played, black first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+	pub size: usize,
+	pub moves: Vec<Position>,
+}
+
+impl Record {
+	pub fn from_game(game: &Game) -> Record {
+		Record {
+			size: game.board.size(),
+			moves: game.moves().iter().map(|undo| undo.position).collect(),
+		}
+	}
+
+	// Plays the moves on a new game, so captures are taken off the board as they happen.
+	pub fn replay(&self, rules: RuleKind, opening: OpeningRule, capture_win: Option<usize>) -> Result<Game, GomokuError> {
+		let mut game = Game::new(self.size, rules, opening, capture_win)?;
+
+		for (i, pos) in self.moves.iter().enumerate() {
+			game.play(*pos)
+				.map_err(|e| GomokuError::InvalidNotation(format!("move {} ({}): {}", i + 1, pos, e)))?;
+		}
+
+		Ok(game)
+	}
+}
+
+impl Notation {
+	pub fn write(self, record: &Record) -> String {
+		match self {
+			Notation::Psq => write_psq(record),
+			Notation::Sgf => write_sgf(record),
+			Notation::Moves => record.moves.iter().map(Position::to_string).collect::<Vec<_>>().join(" "),
+		}
+	}
+
+	// `size` is used by notations that do not carry the board size.
+	pub fn read(self, text: &str, size: usize) -> Result<Record, GomokuError> {
+		match self {
+			Notation::Psq => read_psq(text),
+			Notation::Sgf => read_sgf(text),
+			Notation::Moves => read_moves(text, size),
+		}
+	}
+}
+
+fn position(x: usize, y: usize, size: usize) -> Result<Position, GomokuError> {
+	if x >= size || y >= size {
+		return Err(GomokuError::InvalidNotation(format!("({}, {}) is outside the {}x{} board", x, y, size, size)));
+	}
+
+	Ok(Position::with_size(x, y, size))
+}
+
+fn write_psq(record: &Record) -> String {
+	let mut text = format!("Piskvorky {}x{}, 11:11, 0\n", record.size, record.size);
+
+	for pos in &record.moves {
+		text += &format!("{},{},0\n", pos.x + 1, pos.y + 1);
+	}
+
+	text + "-1\n"
+}
+
+fn read_psq(text: &str) -> Result<Record, GomokuError> {
+	let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+
+	let header = lines.next().unwrap_or_default();
+	let size = header.strip_prefix("Piskvorky ")
+		.and_then(|rest| rest.split(',').next())
+		.and_then(|dims| dims.split_once('x'))
+		.and_then(|(w, h)| Some((w.trim().parse::<usize>().ok()?, h.trim().parse::<usize>().ok()?)))
+		.ok_or_else(|| GomokuError::InvalidNotation(format!("not a psq header: {}", header)))?;
+
+	if size.0 != size.1 {
+		return Err(GomokuError::InvalidNotation(format!("only square boards are supported, not {}x{}", size.0, size.1)));
+	}
+
+	let size = size.0;
+	let mut moves = Vec::new();
+
+	Board::check_size(size)?;
+
+	// the moves end with `-1` or with the first line that is not one, managers append brain names after them
+	for line in lines {
+		let fields: Vec<_> = line.split(',').map(|f| f.trim().parse::<usize>().ok()).collect();
+
+		let (Some(Some(x)), Some(Some(y))) = (fields.first(), fields.get(1)) else {
+			break;
+		};
+
+		if *x == 0 || *y == 0 {
+			return Err(GomokuError::InvalidNotation(format!("psq coordinates start at 1: {}", line)));
+		}
+
+		moves.push(position(x - 1, y - 1, size)?);
+	}
+
+	Ok(Record { size, moves })
+}
+
+fn write_sgf(record: &Record) -> String {
+	let mut text = format!("(;FF[4]GM[4]SZ[{}]", record.size);
+
+	for (i, pos) in record.moves.iter().enumerate() {
+		let color = if i % 2 == 0 {'B'} else {'W'};
+
+		text += &format!(";{}[{}{}]", color, (b'a' + pos.x as u8) as char, (b'a' + pos.y as u8) as char);
+	}
+
+	text + ")\n"
+}
+
+// Reads the main line of the game tree, the moves up to the first closed variation.
+fn read_sgf(text: &str) -> Result<Record, GomokuError> {
+	let mut size = None;
+	let mut moves: Vec<(char, String)> = Vec::new();
+	let mut chars = text.chars().peekable();
+	let mut ident = String::new();
+
+	while let Some(c) = chars.next() {
+		match c {
+			')' => break,
+			'[' => {
+				let mut value = String::new();
+
+				while let Some(c) = chars.next() {
+					match c {
+						']' => break,
+						'\\' => value.extend(chars.next()),
+						_ => value.push(c),
+					}
+				}
+
+				match ident.as_str() {
+					"SZ" => size = Some(value.trim().parse::<usize>()
+						.map_err(|_| GomokuError::InvalidNotation(format!("invalid board size SZ[{}]", value)))?),
+					"GM" if value.trim() != "4" => {
+						return Err(GomokuError::InvalidNotation(format!("GM[{}] is not a gomoku record", value)));
+					},
+					"B" | "W" => moves.push((ident.chars().next().unwrap(), value)),
+					"AB" | "AW" | "AE" => {
+						return Err(GomokuError::InvalidNotation("setup stones are not supported".to_string()));
+					},
+					_ => (),
+				}
+
+				// a property may have several values, `AB[aa][bb]`
+				if chars.peek() != Some(&'[') {
+					ident.clear();
+				}
+			},
+			c if c.is_ascii_uppercase() => ident.push(c),
+			_ => (),
+		}
+	}
+
+	let size = size.unwrap_or(DEFAULT_SIZE);
+
+	Board::check_size(size)?;
+
+	let mut positions = Vec::new();
+
+	for (i, (color, value)) in moves.iter().enumerate() {
+		let expected = if i % 2 == 0 {'B'} else {'W'};
+
+		if *color != expected {
+			return Err(GomokuError::InvalidNotation(format!("move {} is played by {} instead of {}", i + 1, color, expected)));
+		}
+
+		let coords: Vec<_> = value.bytes().collect();
+
+		let [x, y] = coords[..] else {
+			return Err(GomokuError::InvalidNotation(format!("invalid move {}[{}]", color, value)));
+		};
+
+		if !x.is_ascii_lowercase() || !y.is_ascii_lowercase() {
+			return Err(GomokuError::InvalidNotation(format!("invalid move {}[{}]", color, value)));
+		}
+
+		positions.push(position((x - b'a') as usize, (y - b'a') as usize, size)?);
+	}
+
+	Ok(Record { size, moves: positions })
+}
+
+// The reverse of `Position`'s `Display`, case insensitive: the row letter, then the column.
+fn read_moves(text: &str, size: usize) -> Result<Record, GomokuError> {
+	Board::check_size(size)?;
+
+	let moves = text.split(|c: char| c.is_whitespace() || c == ',')
+		.filter(|token| !token.is_empty())
+		.map(|token| {
+			let mut chars = token.chars();
+			let y = chars.next()
+				.filter(char::is_ascii_alphabetic)
+				.map(|c| (c.to_ascii_uppercase() as u8 - b'A') as usize);
+			let x = chars.as_str().parse::<usize>().ok();
+
+			match (x, y) {
+				(Some(x), Some(y)) => position(x, y, size),
+				_ => Err(GomokuError::InvalidNotation(format!("invalid move {}", token))),
+			}
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+
+	Ok(Record { size, moves })
+}
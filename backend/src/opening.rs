@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{board::Board, error::GomokuError, heuristic::{EvalWeights, Heuristic}, minimax::GomokuSolver, piece::{Piece, PieceWrap}, position::Position, ruleset::{self, Ruleset}};
+
+// Tournament opening protocols. The first player is black (`Piece::Max`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpeningRule {
+	// no restrictions
+	#[default]
+	Free,
+	// black starts in the centre, black's second stone at least 3 cells away from it
+	Pro,
+	// as Pro, but at least 4 cells away
+	LongPro,
+	// the first player places three stones, the second player picks a colour
+	Swap,
+	// as Swap, but the second player may instead add two stones and let the first player pick
+	Swap2,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub struct OpeningStone {
+	pub position: Position,
+	pub player: Piece,
+}
+
+// The colour the engine takes, or the stones it adds instead (Swap2 only).
+#[derive(Clone, PartialEq, Debug, Serialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum OpeningDecision {
+	Black,
+	White,
+	Place { stones: Vec<OpeningStone> },
+}
+
+// Search depth used to judge an opening position when the request does not give one.
+pub const DEFAULT_DEPTH: usize = 4;
+// Scores this close to zero count as balanced, which is what a Swap2 player adding stones aims for.
+const BALANCE_MARGIN: f32 = 4.0;
+// Opening stones are added at most this far from the centre.
+const BALANCE_RADIUS: usize = 2;
+
+// The game an opening is played for: rules, capture win and evaluation weights.
+#[derive(Clone, Copy)]
+pub struct OpeningContext {
+	pub rules: &'static dyn Ruleset,
+	// pairs that win instead of the rule set's default, 0 for no capture win
+	pub capture_win: Option<usize>,
+	pub weights: EvalWeights,
+}
+
+pub fn center(board: &Board) -> Position {
+	Position::with_size(board.size() / 2, board.size() / 2, board.size())
+}
+
+fn distance(a: &Position, b: &Position) -> usize {
+	a.x.abs_diff(b.x).max(a.y.abs_diff(b.y))
+}
+
+fn stone_count(board: &Board) -> usize {
+	board.into_iter().filter(|pos| board[pos].is_piece()).count()
+}
+
+// How far from the centre black's second stone has to be.
+fn min_distance(rule: OpeningRule) -> Option<usize> {
+	match rule {
+		OpeningRule::Pro => Some(3),
+		OpeningRule::LongPro => Some(4),
+		_ => None,
+	}
+}
+
+// Whether the opening protocol lets `player` play `pos` on `board`.
+pub fn is_allowed(rule: OpeningRule, board: &Board, pos: Position, player: Piece) -> bool {
+	let Some(min_distance) = min_distance(rule) else {
+		return true;
+	};
+
+	match stone_count(board) {
+		0 => pos == center(board),
+		2 if player.is_max() => distance(&pos, &center(board)) >= min_distance,
+		_ => true,
+	}
+}
+
+// Moves the search has to consider besides the ones next to existing stones:
+// the centre on an empty board, and for Pro openings the closest cells black's
+// second stone may go to.
+pub fn candidates(rule: OpeningRule, board: &Board, player: Piece) -> Vec<Position> {
+	let count = stone_count(board);
+
+	if count == 0 {
+		return vec![center(board)];
+	}
+
+	match min_distance(rule) {
+		Some(min_distance) if count == 2 && player.is_max() => board.into_iter()
+			.filter(|pos| board[pos].is_empty() && distance(pos, &center(board)) == min_distance)
+			.collect(),
+		_ => Vec::new(),
+	}
+}
+
+// The pair of a black and a white stone near the centre that leaves the most balanced position.
+fn balanced_pair(board: &Board, context: &OpeningContext) -> Vec<OpeningStone> {
+	let center = center(board);
+	let cells: Vec<Position> = board.into_iter()
+		.filter(|pos| board[pos].is_empty() && distance(pos, &center) <= BALANCE_RADIUS)
+		.collect();
+	let mut heuristic = Heuristic::with_weights(board, &[0, 0], context.rules, context.weights);
+	let mut best: Option<(f32, Position, Position)> = None;
+
+	heuristic.capture_win = ruleset::capture_win(context.rules, context.capture_win);
+	heuristic.get_heuristic();
+
+	for black in &cells {
+		let black_map = context.rules.capture_map(&heuristic.board, *black, Piece::Max);
+		let black_undo = heuristic.make_move(*black, Piece::Max, Some(black_map));
+
+		for white in &cells {
+			if heuristic.board[white].is_piece() {
+				continue;
+			}
+
+			let white_map = context.rules.capture_map(&heuristic.board, *white, Piece::Min);
+			let white_undo = heuristic.make_move(*white, Piece::Min, Some(white_map));
+			let score = heuristic.get_heuristic().abs();
+
+			heuristic.unmake_move(white_undo);
+
+			if best.is_none_or(|(s, _, _)| score < s) {
+				best = Some((score, *black, *white));
+			}
+		}
+
+		heuristic.unmake_move(black_undo);
+	}
+
+	best.map(|(_, black, white)| vec![
+		OpeningStone { position: black, player: Piece::Max },
+		OpeningStone { position: white, player: Piece::Min },
+	]).unwrap_or_default()
+}
+
+// The stones the first player opens a Swap or Swap2 game with: black in the
+// centre, then the white and black stone that keep the position most even,
+// since the opponent picks the colour.
+pub fn propose(rule: OpeningRule, board: &Board, context: &OpeningContext) -> Result<Vec<OpeningStone>, GomokuError> {
+	if !matches!(rule, OpeningRule::Swap | OpeningRule::Swap2) {
+		return Err(GomokuError::InvalidOpening(format!("{:?} has no opening placement", rule)));
+	}
+	if stone_count(board) != 0 {
+		return Err(GomokuError::InvalidOpening("the opening stones are placed on an empty board".to_string()));
+	}
+
+	let center = center(board);
+	let mut board = board.clone();
+	board.set_move(center, Piece::Max, Some(0));
+
+	let pair = balanced_pair(&board, context);
+	let mut stones = vec![OpeningStone { position: center, player: Piece::Max }];
+	stones.extend(pair.into_iter().rev());
+
+	Ok(stones)
+}
+
+// The colour decision of the player to choose. With white to move, a positive
+// score means black is ahead.
+pub fn choose(rule: OpeningRule, board: &Board, context: &OpeningContext, depth: usize) -> Result<OpeningDecision, GomokuError> {
+	let count = stone_count(board);
+
+	match (rule, count) {
+		(OpeningRule::Swap, 3) | (OpeningRule::Swap2, 3) | (OpeningRule::Swap2, 5) => (),
+		_ => return Err(GomokuError::InvalidOpening(format!("no colour choice in {:?} with {} stones", rule, count))),
+	}
+
+	let mut solver = GomokuSolver::new(board.clone(), [0, 0], Piece::Min, depth);
+
+	solver.is_hint = Some(true);
+	solver.rules = context.rules;
+	solver.capture_win = context.capture_win;
+	solver.weights = context.weights;
+
+	let score = solver.solve().map_err(|e| GomokuError::InvalidOpening(e.to_string()))?.score;
+
+	if rule == OpeningRule::Swap2 && count == 3 && score.abs() <= BALANCE_MARGIN {
+		return Ok(OpeningDecision::Place { stones: balanced_pair(board, context) });
+	}
+
+	Ok(if score > 0.0 {OpeningDecision::Black} else {OpeningDecision::White})
+}
@@ -0,0 +1,115 @@
+use std::io;
+
+use crate::{board::Board, position::Position, ruleset::RuleKind};
+
+// A line of the Gomocup / Piskvork brain protocol, as sent by the tournament manager.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+	// new game on a board of this size
+	Start(usize),
+	Restart,
+	// we play the first move
+	Begin,
+	// the opponent's move, ours is expected back
+	Turn(Position),
+	// a whole position follows, one `x,y,who` line per stone up to DONE
+	Board,
+	// the move to take back, which has to be the last one
+	Takeback(Position),
+	Info(Info),
+	About,
+	End,
+	// blank lines are skipped
+	Empty,
+	Unknown(String),
+}
+
+// The `INFO key value` settings a brain acts on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Info {
+	// milliseconds per turn, 0 asks for a move as fast as possible
+	TimeoutTurn(u64),
+	// milliseconds left for the whole match
+	TimeLeft(u64),
+	Rule(RuleKind),
+	// keys we do not use and values we cannot read
+	Ignored,
+}
+
+// Parses one protocol line. Coordinates are checked against `board`, errors
+// are the text of the `ERROR` reply.
+pub fn parse_command(board: &Board, line: &str) -> Result<Command, String> {
+	let line = line.trim();
+	let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+
+	Ok(match command.to_uppercase().as_str() {
+		"START" => {
+			let size = args.trim().parse::<usize>().map_err(|_| format!("invalid board size '{}'", args.trim()))?;
+
+			Board::check_size(size).map_err(|e| e.to_string())?;
+			Command::Start(size)
+		},
+		"RESTART" => Command::Restart,
+		"BEGIN" => Command::Begin,
+		"TURN" => Command::Turn(parse_position(board, args)?),
+		"BOARD" => Command::Board,
+		"TAKEBACK" => Command::Takeback(parse_position(board, args)?),
+		"INFO" => Command::Info(parse_info(args)),
+		"ABOUT" => Command::About,
+		"END" => Command::End,
+		"" => Command::Empty,
+		_ => Command::Unknown(command.to_string()),
+	})
+}
+
+fn parse_info(args: &str) -> Info {
+	let (key, value) = args.split_once(' ').unwrap_or((args, ""));
+
+	match (key.to_lowercase().as_str(), value.trim().parse::<u64>()) {
+		("timeout_turn", Ok(ms)) => Info::TimeoutTurn(ms),
+		("time_left", Ok(ms)) => Info::TimeLeft(ms),
+		("rule", Ok(rule)) => Info::Rule(rule_kind(rule)),
+		_ => Info::Ignored,
+	}
+}
+
+// Bit 0 of `INFO rule` asks for exactly five, bit 2 for renju, the other bits are not supported.
+pub fn rule_kind(rule: u64) -> RuleKind {
+	if rule & 4 == 4 {
+		RuleKind::Renju
+	} else if rule & 1 == 1 {
+		RuleKind::Gomoku
+	} else {
+		RuleKind::Freestyle
+	}
+}
+
+pub fn parse_position(board: &Board, text: &str) -> Result<Position, String> {
+	let mut coords = text.trim().split(',').map(|c| c.trim().parse::<usize>());
+
+	match (coords.next(), coords.next(), coords.next()) {
+		(Some(Ok(x)), Some(Ok(y)), None) => board.position(x, y),
+		_ => None,
+	}.ok_or(format!("invalid coordinates '{}'", text.trim()))
+}
+
+// Reads the `x,y,who` lines of a BOARD command up to DONE. `who` is 1 for our
+// stones and 2 for the opponent's, the flag is true for ours.
+pub fn read_board(board: &Board, lines: &mut impl Iterator<Item = io::Result<String>>) -> Result<Vec<(Position, bool)>, String> {
+	let mut stones = Vec::new();
+
+	for line in lines {
+		let line = line.map_err(|e| e.to_string())?;
+		let line = line.trim();
+
+		if line.eq_ignore_ascii_case("DONE") {
+			return Ok(stones);
+		}
+
+		let (pos, who) = line.rsplit_once(',').ok_or(format!("invalid board line '{}'", line))?;
+
+		stones.push((parse_position(board, pos)?, who.trim() != "2"));
+	}
+
+	Err("BOARD was not closed by DONE".to_string())
+}
@@ -2,6 +2,8 @@ use std::fmt;
 
 use serde_repr::*;
 
+use crate::error::GomokuError;
+
 #[derive(Clone, Copy, PartialEq, Deserialize_repr, Serialize_repr, Debug)]
 #[repr(i8)]
 pub enum Piece {
@@ -33,6 +35,15 @@ impl TryFrom<u64> for Piece {
 	}
 }
 
+// The side a request plays for, which has to be black or white.
+pub fn check_player(player: Piece) -> Result<Piece, GomokuError> {
+	if player.is_empty() {
+		return Err(GomokuError::InvalidJson("player must be 0 or 1".to_string()));
+	}
+
+	Ok(player)
+}
+
 impl fmt::Display for Piece {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let to_write = match self {
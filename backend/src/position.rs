@@ -1,25 +1,60 @@
-use std::fmt;
+use std::{fmt, hash::{Hash, Hasher}};
 
 use serde::{Deserialize, Serialize};
 
 
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Debug)]
+// Side length of the standard board, used when a request or board does not give one.
+pub const DEFAULT_SIZE: usize = 19;
+// Largest supported side length. Gomocup plays on 20x20.
+pub const MAX_SIZE: usize = 20;
+// Smallest board a five still fits on.
+pub const MIN_SIZE: usize = 5;
+
+pub fn default_size() -> usize {
+	DEFAULT_SIZE
+}
+
+// A cell of a board with side length `size`. Only `x` and `y` identify the cell,
+// `size` bounds `relocate` and is not sent to clients.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
 pub struct Position {
 	pub x: usize,
 	pub y: usize,
+	#[serde(skip, default = "default_size")]
+	size: usize,
+}
+
+impl PartialEq for Position {
+	fn eq(&self, other: &Self) -> bool {
+		self.x == other.x && self.y == other.y
+	}
+}
+
+impl Eq for Position {}
+
+impl Hash for Position {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.x.hash(state);
+		self.y.hash(state);
+	}
 }
 
 impl Position {
 	pub fn new(x: usize, y: usize) -> Position {
-		return *Position {
-			x: x,
-			y: y,
-		}.check_pos().unwrap();
+		Self::with_size(x, y, DEFAULT_SIZE)
+	}
+
+	pub fn with_size(x: usize, y: usize, size: usize) -> Position {
+		*Position { x, y, size }.check_pos().unwrap()
+	}
+
+	pub fn size(&self) -> usize {
+		self.size
 	}
 
 	pub fn check_pos(&self) -> Result<&Position, &str> {
-		if self.x >= 19 || self.y >= 19 {
+		if self.x >= self.size || self.y >= self.size {
 			return Err("Invalid position");
 		}
 		return Ok(self);
@@ -42,21 +77,25 @@ impl Position {
 		return Ok(self);
 	}
 
-	pub fn to_u64(&self) -> u64 {
-		return (self.y * 19 + self.x) as u64;
+	// Index of the cell on a board with side length `size`, the inverse of `from_u64`.
+	// Deserialized positions carry the default size, so the board's is passed in.
+	pub fn to_u64(&self, size: usize) -> u64 {
+		return (self.y * size + self.x) as u64;
 	}
 
-	pub fn from_u64(pos: usize) -> Position {
+	pub fn from_u64(pos: usize, size: usize) -> Position {
 		return *Position {
-			x: pos % 19,
-			y: pos.div_euclid(19)
+			x: pos % size,
+			y: pos.div_euclid(size),
+			size,
 		}.check_pos().unwrap();
 	}
 }
 
 impl fmt::Display for Position {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		if self.y >= 19 || self.x >= 19 {
+		// any supported board, deserialized positions do not know theirs
+		if self.x >= MAX_SIZE || self.y >= MAX_SIZE {
 			write!(f, "Invalid Position")
 		} else {
 			let y_char = (self.y as u8) + 65;
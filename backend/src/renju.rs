@@ -0,0 +1,118 @@
+use crate::{board::Board, piece::Piece, position::Position};
+
+// Forbidden moves of Renju. Only black, the first player (`Piece::Max`), is
+// restricted: a move is forbidden when it makes an overline, two fours or two
+// threes, unless it also makes exactly five. A three only counts when it can
+// still become a straight four through a move that is not forbidden itself.
+
+const DIRECTIONS: [[i32; 2]; 4] = [
+	[1, 0], //x
+	[0, 1], //y
+	[1, 1], //tlbr
+	[1, -1], //trbl
+];
+
+const BLACK: Piece = Piece::Max;
+
+// Length of the black run through `pos` along `direction`, `pos` included.
+fn run_length(board: &Board, pos: Position, direction: [i32; 2]) -> usize {
+	let mut length = 1;
+
+	for sign in [1, -1] {
+		let mut cur = pos;
+
+		while cur.relocate(direction[0] * sign, direction[1] * sign).is_ok() && board[&cur] == BLACK {
+			length += 1;
+		}
+	}
+
+	length
+}
+
+// Empty cells that would complete exactly five together with the black run through `pos`.
+// Only the first cell past each end of the run can, so there are at most two.
+fn five_points(board: &mut Board, pos: Position, direction: [i32; 2]) -> Vec<Position> {
+	let mut points = Vec::with_capacity(2);
+
+	for sign in [1, -1] {
+		let mut cur = pos;
+
+		while cur.relocate(direction[0] * sign, direction[1] * sign).is_ok() && board[&cur] == BLACK {}
+
+		if cur.check_pos().is_err() || board[&cur] != Piece::Empty {
+			continue;
+		}
+
+		let undo = board.make_move(cur, BLACK, Some(0));
+		if run_length(board, cur, direction) == 5 {
+			points.push(cur);
+		}
+		board.unmake_move(&undo);
+	}
+
+	points
+}
+
+// Fours through `pos` along `direction`. `.XXXX.` is a single (straight) four,
+// two five points around a shorter run are two fours on one line.
+fn four_count(board: &mut Board, pos: Position, direction: [i32; 2]) -> usize {
+	let points = five_points(board, pos, direction);
+
+	if points.len() == 2 && run_length(board, pos, direction) == 4 {
+		return 1;
+	}
+
+	points.len()
+}
+
+fn is_straight_four(board: &mut Board, pos: Position, direction: [i32; 2]) -> bool {
+	run_length(board, pos, direction) == 4 && five_points(board, pos, direction).len() == 2
+}
+
+// Whether the black stone on `pos` forms a three along `direction`: some empty
+// cell on the line turns it into a straight four and is allowed for black.
+fn is_three(board: &mut Board, pos: Position, direction: [i32; 2]) -> bool {
+	if four_count(board, pos, direction) > 0 {
+		return false;
+	}
+
+	for sign in [1, -1] {
+		let mut cur = pos;
+
+		for _ in 0..4 {
+			if cur.relocate(direction[0] * sign, direction[1] * sign).is_err() || board[&cur] == Piece::Min {
+				break;
+			}
+			if board[&cur] != Piece::Empty {
+				continue;
+			}
+
+			let undo = board.make_move(cur, BLACK, Some(0));
+			let straight = is_straight_four(board, pos, direction);
+			board.unmake_move(&undo);
+
+			if straight && !is_forbidden(board, cur) {
+				return true;
+			}
+		}
+	}
+
+	false
+}
+
+// Whether black may not play the empty cell `pos`. `board` is left unchanged.
+pub fn is_forbidden(board: &mut Board, pos: Position) -> bool {
+	let undo = board.make_move(pos, BLACK, Some(0));
+
+	let lengths = DIRECTIONS.map(|d| run_length(board, pos, d));
+
+	let forbidden = !lengths.contains(&5) && (
+		lengths.iter().any(|l| *l > 5) ||
+		DIRECTIONS.iter().map(|d| four_count(board, pos, *d)).sum::<usize>() >= 2 ||
+		DIRECTIONS.iter().filter(|d| is_three(board, pos, **d)).count() >= 2
+	);
+
+	board.unmake_move(&undo);
+
+	forbidden
+}
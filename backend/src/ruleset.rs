@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{board::Board, heuristic::Heuristic, piece::{Piece, PieceWrap}, position::Position, renju};
+
+// The rules a game is played under, as sent by clients.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleKind {
+	// the rules of the 42 subject: captures, ten captured stones win, no double free threes
+	#[default]
+	#[serde(rename = "42")]
+	FortyTwo,
+	// exactly five wins, no captures
+	Gomoku,
+	// five or more wins, no captures
+	Freestyle,
+	// captures and a capture win, no double free threes for the first player, whose
+	// overlines do not win either
+	NinukiRenju,
+	// no captures, the first player may not make overlines, double fours or double threes
+	Renju,
+}
+
+impl RuleKind {
+	pub fn ruleset(&self) -> &'static dyn Ruleset {
+		match self {
+			RuleKind::FortyTwo => &FortyTwo,
+			RuleKind::Gomoku => &Gomoku,
+			RuleKind::Freestyle => &Freestyle,
+			RuleKind::NinukiRenju => &NinukiRenju,
+			RuleKind::Renju => &Renju,
+		}
+	}
+}
+
+// The pairs that win under `rules` when a game asks for `requested` pairs instead
+// of the rule set's default. 0 plays with captures but without a capture win.
+pub fn capture_win(rules: &dyn Ruleset, requested: Option<usize>) -> Option<usize> {
+	if !rules.captures() {
+		return None;
+	}
+
+	match requested {
+		None => rules.capture_win(),
+		Some(0) => None,
+		Some(pairs) => Some(pairs),
+	}
+}
+
+pub trait Ruleset: Send + Sync {
+	fn kind(&self) -> RuleKind;
+
+	// Whether flanking a pair of opponent stones removes it.
+	fn captures(&self) -> bool;
+
+	// Pairs a player has to capture to win by default, `None` when captures never win.
+	fn capture_win(&self) -> Option<usize>;
+
+	// Whether a run of `length` stones of `player` wins. Rule sets differ on overlines.
+	fn is_winning_length(&self, length: usize, player: Piece) -> bool;
+
+	// Whether `player` may play the empty cell `pos`.
+	fn is_legal(&self, heuristic: &mut Heuristic, pos: Position, player: Piece) -> bool;
+
+	// What `Board::get_captures` returns for the move, or no captures at all.
+	fn capture_map(&self, board: &Board, pos: Position, player: Piece) -> u8 {
+		if self.captures() {
+			Board::get_captures(board, pos, player)
+		} else {
+			0
+		}
+	}
+}
+
+pub struct FortyTwo;
+
+impl Ruleset for FortyTwo {
+	fn kind(&self) -> RuleKind {
+		RuleKind::FortyTwo
+	}
+
+	fn captures(&self) -> bool {
+		true
+	}
+
+	fn capture_win(&self) -> Option<usize> {
+		Some(5)
+	}
+
+	fn is_winning_length(&self, length: usize, _player: Piece) -> bool {
+		length >= 5
+	}
+
+	fn is_legal(&self, heuristic: &mut Heuristic, pos: Position, player: Piece) -> bool {
+		!heuristic.is_double_three(pos, player)
+	}
+}
+
+pub struct Gomoku;
+
+impl Ruleset for Gomoku {
+	fn kind(&self) -> RuleKind {
+		RuleKind::Gomoku
+	}
+
+	fn captures(&self) -> bool {
+		false
+	}
+
+	fn capture_win(&self) -> Option<usize> {
+		None
+	}
+
+	fn is_winning_length(&self, length: usize, _player: Piece) -> bool {
+		length == 5
+	}
+
+	fn is_legal(&self, _heuristic: &mut Heuristic, _pos: Position, _player: Piece) -> bool {
+		true
+	}
+}
+
+pub struct Freestyle;
+
+impl Ruleset for Freestyle {
+	fn kind(&self) -> RuleKind {
+		RuleKind::Freestyle
+	}
+
+	fn captures(&self) -> bool {
+		false
+	}
+
+	fn capture_win(&self) -> Option<usize> {
+		None
+	}
+
+	fn is_winning_length(&self, length: usize, _player: Piece) -> bool {
+		length >= 5
+	}
+
+	fn is_legal(&self, _heuristic: &mut Heuristic, _pos: Position, _player: Piece) -> bool {
+		true
+	}
+}
+
+pub struct NinukiRenju;
+
+impl Ruleset for NinukiRenju {
+	fn kind(&self) -> RuleKind {
+		RuleKind::NinukiRenju
+	}
+
+	fn captures(&self) -> bool {
+		true
+	}
+
+	fn capture_win(&self) -> Option<usize> {
+		Some(5)
+	}
+
+	// Like Renju, only the second player wins with an overline.
+	fn is_winning_length(&self, length: usize, player: Piece) -> bool {
+		length == 5 || (length > 5 && player.is_min())
+	}
+
+	fn is_legal(&self, heuristic: &mut Heuristic, pos: Position, player: Piece) -> bool {
+		player.is_min() || !heuristic.is_double_three(pos, player)
+	}
+}
+
+pub struct Renju;
+
+impl Ruleset for Renju {
+	fn kind(&self) -> RuleKind {
+		RuleKind::Renju
+	}
+
+	fn captures(&self) -> bool {
+		false
+	}
+
+	fn capture_win(&self) -> Option<usize> {
+		None
+	}
+
+	// Overlines are forbidden for the first player and win for the second.
+	fn is_winning_length(&self, length: usize, player: Piece) -> bool {
+		length == 5 || (length > 5 && player.is_min())
+	}
+
+	fn is_legal(&self, heuristic: &mut Heuristic, pos: Position, player: Piece) -> bool {
+		player.is_min() || !renju::is_forbidden(&mut heuristic.board, pos)
+	}
+}
@@ -0,0 +1,480 @@
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, time::Instant};
+
+use rustc_hash::FxHashSet;
+
+use crate::{board::{Board, MoveUndo}, heuristic::Heuristic, piece::{Piece, PieceWrap}, position::Position, ruleset::Ruleset};
+
+const DIRECTIONS: [[i32; 2]; 4] = [
+	[1, 0], //x
+	[0, 1], //y
+	[1, 1], //tlbr
+	[1, -1], //trbl
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ThreatKind {
+	// continuous fours only
+	Vcf,
+	// threes and fours
+	Vct,
+}
+
+fn run_length(board: &Board, pos: Position, direction: [i32; 2], player: Piece) -> usize {
+	let mut length = 0;
+
+	for sign in [1, -1] {
+		let mut cur = pos;
+
+		while cur.relocate(direction[0] * sign, direction[1] * sign).is_ok() && board[&cur] == player {
+			length += 1;
+		}
+	}
+
+	length + 1
+}
+
+// The stones of the run through `pos` in `direction`, `pos` included.
+fn run_stones(board: &Board, pos: Position, direction: [i32; 2], player: Piece) -> Vec<Position> {
+	let mut stones = vec![pos];
+
+	for sign in [1, -1] {
+		let mut cur = pos;
+
+		while cur.relocate(direction[0] * sign, direction[1] * sign).is_ok() && board[&cur] == player {
+			stones.push(cur);
+		}
+	}
+
+	stones
+}
+
+// Empty cells on the line through `pos` in `direction` where `player` would complete a five.
+fn five_squares(board: &mut Board, pos: Position, direction: [i32; 2], player: Piece, rules: &dyn Ruleset) -> Vec<Position> {
+	let mut squares = Vec::new();
+
+	for sign in [1, -1] {
+		let mut cur = pos;
+
+		for _ in 0..4 {
+			if cur.relocate(direction[0] * sign, direction[1] * sign).is_err() {
+				break;
+			}
+			if board[&cur].is_empty() && rules.is_winning_length(run_length_if_placed(board, cur, direction, player), player) {
+				squares.push(cur);
+			}
+		}
+	}
+
+	squares
+}
+
+fn run_length_if_placed(board: &mut Board, pos: Position, direction: [i32; 2], player: Piece) -> usize {
+	board[&pos] = player;
+	let length = run_length(board, pos, direction, player);
+	board[&pos] = Piece::Empty;
+
+	length
+}
+
+// Empty cells within four steps of a stone of `player` along any direction.
+fn candidates(board: &Board, player: Piece) -> Vec<Position> {
+	let mut cells = FxHashSet::default();
+
+	for pos in board {
+		if board[&pos] != player {
+			continue;
+		}
+
+		for direction in DIRECTIONS {
+			for sign in [1, -1] {
+				let mut cur = pos;
+
+				for _ in 0..4 {
+					if cur.relocate(direction[0] * sign, direction[1] * sign).is_err() {
+						break;
+					}
+					if board[&cur].is_empty() {
+						cells.insert(cur);
+					}
+				}
+			}
+		}
+	}
+
+	let mut cells: Vec<Position> = cells.into_iter().collect();
+	cells.sort_by_key(|p| p.to_u64(board.size()));
+	cells
+}
+
+// Cells within capture reach of `pos`, where a new stone can create or remove capture patterns.
+fn nearby(pos: Position) -> Vec<Position> {
+	let mut cells = Vec::with_capacity(24);
+
+	for direction in DIRECTIONS {
+		for sign in [1, -1] {
+			let mut cur = pos;
+
+			for _ in 0..3 {
+				if cur.relocate(direction[0] * sign, direction[1] * sign).is_err() {
+					break;
+				}
+				cells.push(cur);
+			}
+		}
+	}
+
+	cells
+}
+
+struct Threat {
+	is_four: bool,
+	replies: Vec<Position>,
+}
+
+// Defender resources at an attacker node, computed once before its threats are tried.
+struct AttackContext {
+	defender_fives: Vec<Position>,
+	defender_captures: Vec<Position>,
+	defender_capture_wins: Vec<Position>,
+	defender_has_four: bool,
+}
+
+pub struct ThreatSolver {
+	pub board: Board,
+	pub captures: [usize; 2],
+	pub attacker: Piece,
+	pub rules: &'static dyn Ruleset,
+	// pairs that win the game, the rule set's default unless set
+	pub capture_win: Option<usize>,
+	pub nodes: usize,
+	pub max_nodes: usize,
+	// the search gives up once `stop` is set or `deadline` has passed
+	pub stop: Arc<AtomicBool>,
+	pub deadline: Option<Instant>,
+}
+
+impl ThreatSolver {
+	pub fn new(board: &Board, captures: [usize; 2], attacker: Piece, rules: &'static dyn Ruleset) -> ThreatSolver {
+		ThreatSolver {
+			board: board.clone(),
+			captures,
+			attacker,
+			rules,
+			capture_win: rules.capture_win(),
+			nodes: 0,
+			max_nodes: 200_000,
+			stop: Arc::new(AtomicBool::new(false)),
+			deadline: None,
+		}
+	}
+
+	// Searches for a forced win of at most `depth` attacker moves. The result holds
+	// attacker and defender moves alternately, ending with the winning move or with a
+	// threat the defender has no reply to.
+	pub fn solve(&mut self, kind: ThreatKind, depth: usize) -> Option<Vec<Position>> {
+		self.nodes = 0;
+
+		self.attack(kind, depth)
+	}
+
+	fn is_stopped(&self) -> bool {
+		self.nodes >= self.max_nodes ||
+			self.stop.load(Ordering::Relaxed) ||
+			self.deadline.is_some_and(|d| Instant::now() >= d)
+	}
+
+	fn capture_count(&self, pos: Position, player: Piece) -> usize {
+		self.rules.capture_map(&self.board, pos, player).count_ones() as usize
+	}
+
+	// Whether `player` reaches the capture win by playing `pos`.
+	fn wins_by_capture(&self, pos: Position, player: Piece) -> bool {
+		self.capture_win.is_some_and(|win| self.captures[player as usize] + self.capture_count(pos, player) >= win)
+	}
+
+	fn is_five(&self, pos: Position, direction: [i32; 2], player: Piece) -> bool {
+		self.rules.is_winning_length(run_length(&self.board, pos, direction, player), player)
+	}
+
+	fn makes_five(&mut self, pos: Position, player: Piece) -> bool {
+		DIRECTIONS.iter().any(|d| self.rules.is_winning_length(run_length_if_placed(&mut self.board, pos, *d, player), player))
+	}
+
+	fn make_move(&mut self, pos: Position, player: Piece) -> MoveUndo {
+		let capture_map = self.rules.capture_map(&self.board, pos, player);
+
+		self.board.make_move(pos, player, Some(capture_map))
+	}
+
+	// Moves of `player` that remove at least one stone of the other side.
+	fn capture_moves(&self, player: Piece) -> Vec<Position> {
+		candidates(&self.board, player.get_opposite()).into_iter()
+			.filter(|pos| self.capture_count(*pos, player) > 0)
+			.collect()
+	}
+
+	// A five of `player` through `pos` that the opponent can take apart with a capture.
+	fn breaking_captures(&mut self, pos: Position, player: Piece) -> Vec<Position> {
+		let mut stones = FxHashSet::default();
+
+		for direction in DIRECTIONS {
+			if self.is_five(pos, direction, player) {
+				stones.extend(run_stones(&self.board, pos, direction, player));
+			}
+		}
+
+		self.capture_moves(player.get_opposite()).into_iter().filter(|capture| {
+			let undo = self.make_move(*capture, player.get_opposite());
+			let breaks = undo.captured.iter().any(|p| stones.contains(p));
+			self.board.unmake_move(&undo);
+			breaks
+		}).collect()
+	}
+
+	// Moves that win on the spot: an unbreakable five or the last capture needed.
+	fn winning_moves(&mut self, player: Piece) -> Vec<Position> {
+		let mut moves = Vec::new();
+
+		for pos in candidates(&self.board, player) {
+			if self.wins_by_capture(pos, player) {
+				moves.push(pos);
+				continue;
+			}
+
+			if self.makes_five(pos, player) {
+				let undo = self.make_move(pos, player);
+				let breakable = !self.breaking_captures(pos, player).is_empty();
+				self.board.unmake_move(&undo);
+
+				if !breakable {
+					moves.push(pos);
+				}
+			}
+		}
+
+		moves
+	}
+
+	// Whether `player` can turn the line through `pos` into a straight four (two five squares).
+	fn can_make_straight_four(&mut self, pos: Position, player: Piece) -> bool {
+		for direction in DIRECTIONS {
+			for sign in [1, -1] {
+				let mut cur = pos;
+
+				for _ in 0..4 {
+					if cur.relocate(direction[0] * sign, direction[1] * sign).is_err() {
+						break;
+					}
+					if self.board[&cur].is_piece() {
+						continue;
+					}
+
+					self.board[&cur] = player;
+					let squares = five_squares(&mut self.board, cur, direction, player, self.rules).len();
+					self.board[&cur] = Piece::Empty;
+
+					if squares >= 2 {
+						return true;
+					}
+				}
+			}
+		}
+
+		false
+	}
+
+	// Defender replies that have to be tried against the threat just played at `pos`.
+	// `None` means the move is no threat at all, no replies that nothing stops it.
+	fn forced_replies(&mut self, pos: Position, kind: ThreatKind, ctx: &AttackContext) -> Option<Threat> {
+		let attacker = self.attacker;
+		let defender = attacker.get_opposite();
+
+		if DIRECTIONS.iter().any(|d| self.is_five(pos, *d, attacker)) {
+			return Some(Threat { is_four: true, replies: self.breaking_captures(pos, attacker) });
+		}
+
+		let mut fours = Vec::new();
+		for direction in DIRECTIONS {
+			fours.extend(five_squares(&mut self.board, pos, direction, attacker, self.rules));
+		}
+
+		let is_four = !fours.is_empty();
+
+		if !is_four && (kind == ThreatKind::Vcf || ctx.defender_has_four || !self.can_make_straight_four(pos, attacker)) {
+			return None;
+		}
+
+		let mut replies: FxHashSet<Position> = ctx.defender_captures.iter()
+			.chain(nearby(pos).iter())
+			.filter(|p| self.board[p].is_empty() && self.capture_count(**p, defender) > 0)
+			.copied()
+			.collect();
+
+		if is_four {
+			replies.extend(fours);
+		} else {
+			for direction in DIRECTIONS {
+				for sign in [1, -1] {
+					let mut cur = pos;
+
+					for _ in 0..5 {
+						if cur.relocate(direction[0] * sign, direction[1] * sign).is_err() {
+							break;
+						}
+						if self.board[&cur].is_piece() || replies.contains(&cur) {
+							continue;
+						}
+
+						let undo = self.make_move(cur, defender);
+						let defends = !self.can_make_straight_four(pos, attacker);
+						self.board.unmake_move(&undo);
+
+						if defends {
+							replies.insert(cur);
+						}
+					}
+				}
+			}
+		}
+
+		let mut replies: Vec<Position> = replies.into_iter().collect();
+		replies.sort_by_key(|p| p.to_u64(self.board.size()));
+
+		Some(Threat { is_four, replies })
+	}
+
+	// Whether the defender wins right away after the attacker played `pos`.
+	fn defender_wins_after(&self, pos: Position, ctx: &AttackContext) -> bool {
+		let defender = self.attacker.get_opposite();
+
+		if ctx.defender_fives.iter().any(|p| *p != pos) {
+			return true;
+		}
+
+		ctx.defender_capture_wins.iter()
+			.chain(nearby(pos).iter())
+			.any(|p| self.board[p].is_empty() && self.wins_by_capture(*p, defender))
+	}
+
+	fn has_four_move(&mut self, player: Piece) -> bool {
+		candidates(&self.board, player).into_iter().any(|pos| {
+			self.board[&pos] = player;
+			let four = DIRECTIONS.iter().any(|d| !five_squares(&mut self.board, pos, *d, player, self.rules).is_empty());
+			self.board[&pos] = Piece::Empty;
+			four
+		})
+	}
+
+	fn is_legal(&self, pos: Position, player: Piece) -> bool {
+		Heuristic::from_board(&self.board, &self.captures, self.rules).validate_move(pos, player)
+	}
+
+	fn play(&mut self, pos: Position, player: Piece) -> MoveUndo {
+		let undo = self.make_move(pos, player);
+		self.captures[player as usize] += undo.capture_count();
+		undo
+	}
+
+	fn take_back(&mut self, undo: &MoveUndo) {
+		self.captures[undo.player as usize] -= undo.capture_count();
+		self.board.unmake_move(undo);
+	}
+
+	fn attack(&mut self, kind: ThreatKind, depth: usize) -> Option<Vec<Position>> {
+		self.nodes += 1;
+
+		if let Some(win) = self.winning_moves(self.attacker).first() {
+			return Some(vec![*win]);
+		}
+
+		if depth == 0 || self.is_stopped() {
+			return None;
+		}
+
+		let defender = self.attacker.get_opposite();
+		let attacker_cells = candidates(&self.board, self.attacker);
+		let defender_cells = candidates(&self.board, defender);
+
+		let ctx = AttackContext {
+			defender_fives: defender_cells.iter()
+				.filter(|pos| self.makes_five(**pos, defender))
+				.copied()
+				.collect(),
+			defender_captures: attacker_cells.iter()
+				.filter(|pos| self.capture_count(**pos, defender) > 0)
+				.copied()
+				.collect(),
+			defender_capture_wins: attacker_cells.iter()
+				.filter(|pos| self.wins_by_capture(**pos, defender))
+				.copied()
+				.collect(),
+			defender_has_four: kind == ThreatKind::Vct && self.has_four_move(defender),
+		};
+
+		if ctx.defender_fives.len() > 1 {
+			return None;
+		}
+
+		let mut threats = Vec::new();
+
+		for pos in attacker_cells {
+			if ctx.defender_fives.first().is_some_and(|p| *p != pos) {
+				continue;
+			}
+
+			let undo = self.play(pos, self.attacker);
+			let threat = self.forced_replies(pos, kind, &ctx).filter(|_| !self.defender_wins_after(pos, &ctx));
+			self.take_back(&undo);
+
+			if let Some(threat) = threat {
+				threats.push((pos, threat));
+			}
+		}
+
+		// Fours first, they leave the defender the fewest replies.
+		threats.sort_by_key(|(_, threat)| (!threat.is_four, threat.replies.len()));
+
+		for (pos, threat) in threats {
+			if !self.is_legal(pos, self.attacker) {
+				continue;
+			}
+
+			let undo = self.play(pos, self.attacker);
+			let line = self.defend(kind, depth, &threat.replies);
+			self.take_back(&undo);
+
+			if let Some(mut line) = line {
+				line.insert(0, pos);
+				return Some(line);
+			}
+		}
+
+		None
+	}
+
+	// Every reply has to lose; the longest refutation is kept as the main line.
+	// A threat without replies, like a double open three, is won already.
+	fn defend(&mut self, kind: ThreatKind, depth: usize, replies: &[Position]) -> Option<Vec<Position>> {
+		let defender = self.attacker.get_opposite();
+
+		if replies.is_empty() {
+			return Some(Vec::new());
+		}
+		let mut main_line: Option<Vec<Position>> = None;
+
+		for reply in replies {
+			let undo = self.play(*reply, defender);
+			let line = self.attack(kind, depth - 1);
+			self.take_back(&undo);
+
+			let mut line = line?;
+			line.insert(0, *reply);
+
+			if main_line.as_ref().is_none_or(|m| m.len() < line.len()) {
+				main_line = Some(line);
+			}
+		}
+
+		main_line
+	}
+}
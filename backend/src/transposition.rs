@@ -0,0 +1,137 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::position::{Position, MAX_SIZE};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bound {
+	Exact = 0,
+	Lower = 1,
+	Upper = 2,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TTEntry {
+	pub depth: usize,
+	pub bound: Bound,
+	pub score: f32,
+	// distance from the stored node to the position the score was taken from
+	pub mate_distance: usize,
+	pub best_move: Option<Position>,
+}
+
+impl TTEntry {
+	// score: 32 bits, depth: 8, bound: 2, move present: 1, move x: 5, move y: 5,
+	// mate distance: 8, occupied: 1
+	fn pack(&self) -> u64 {
+		let mut data = self.score.to_bits() as u64 | 1 << 61;
+
+		data |= (self.depth.min(0xFF) as u64) << 32;
+		data |= (self.bound as u64) << 40;
+
+		if let Some(pos) = self.best_move {
+			data |= 1 << 42;
+			data |= (pos.x as u64 & 0x1F) << 43;
+			data |= (pos.y as u64 & 0x1F) << 48;
+		}
+
+		data |= (self.mate_distance.min(0xFF) as u64) << 53;
+
+		data
+	}
+
+	fn unpack(data: u64) -> TTEntry {
+		TTEntry {
+			score: f32::from_bits(data as u32),
+			depth: ((data >> 32) & 0xFF) as usize,
+			bound: match (data >> 40) & 0x3 {
+				0 => Bound::Exact,
+				1 => Bound::Lower,
+				_ => Bound::Upper,
+			},
+			best_move: if (data >> 42) & 0x1 == 1 {
+				Some(Position::with_size(((data >> 43) & 0x1F) as usize, ((data >> 48) & 0x1F) as usize, MAX_SIZE))
+			} else {
+				None
+			},
+			mate_distance: ((data >> 53) & 0xFF) as usize,
+		}
+	}
+
+	// Whether the stored score can be returned directly for a search window.
+	pub fn is_usable(&self, depth: usize, alpha: f32, beta: f32) -> bool {
+		if self.depth < depth {
+			return false;
+		}
+
+		match self.bound {
+			Bound::Exact => true,
+			Bound::Lower => self.score >= beta,
+			Bound::Upper => self.score <= alpha,
+		}
+	}
+}
+
+// Fixed-size table shared between search threads without locks. Each slot
+// holds the packed entry next to `key ^ entry`, so a slot torn by two
+// concurrent writers no longer matches its key and is ignored on probe.
+pub struct TranspositionTable {
+	slots: Vec<[AtomicU64; 2]>,
+	mask: usize,
+}
+
+impl TranspositionTable {
+	pub const DEFAULT_SIZE: usize = 1 << 20;
+
+	// `size` is rounded up to the next power of two.
+	pub fn new(size: usize) -> TranspositionTable {
+		let size = size.max(1).next_power_of_two();
+
+		TranspositionTable {
+			slots: (0..size).map(|_| [AtomicU64::new(0), AtomicU64::new(0)]).collect(),
+			mask: size - 1,
+		}
+	}
+
+	fn load(&self, key: u64) -> Option<u64> {
+		let slot = &self.slots[key as usize & self.mask];
+		let check = slot[0].load(Ordering::Relaxed);
+		let data = slot[1].load(Ordering::Relaxed);
+
+		if data == 0 || check ^ data != key {
+			return None;
+		}
+
+		Some(data)
+	}
+
+	pub fn probe(&self, key: u64) -> Option<TTEntry> {
+		self.load(key).map(TTEntry::unpack)
+	}
+
+	// Depth-preferred replacement: a different position always replaces the
+	// slot, the same position only when searched at least as deep.
+	pub fn store(&self, key: u64, entry: TTEntry) {
+		if self.load(key).is_some_and(|data| TTEntry::unpack(data).depth > entry.depth) {
+			return;
+		}
+
+		let slot = &self.slots[key as usize & self.mask];
+		let data = entry.pack();
+
+		slot[0].store(key ^ data, Ordering::Relaxed);
+		slot[1].store(data, Ordering::Relaxed);
+	}
+
+	pub fn clear(&self) {
+		for slot in &self.slots {
+			slot[0].store(0, Ordering::Relaxed);
+			slot[1].store(0, Ordering::Relaxed);
+		}
+	}
+}
+
+impl Default for TranspositionTable {
+	fn default() -> Self {
+		TranspositionTable::new(Self::DEFAULT_SIZE)
+	}
+}
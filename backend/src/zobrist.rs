@@ -0,0 +1,44 @@
+use crate::{piece::Piece, position::{Position, MAX_SIZE}};
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+	let state = state.wrapping_add(0x9E3779B97F4A7C15);
+	let mut z = state;
+	z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+	(state, z ^ (z >> 31))
+}
+
+const fn generate<const N: usize>(seed: u64) -> [[u64; 2]; N] {
+	let mut keys = [[0u64; 2]; N];
+	let mut state = seed;
+	let mut i = 0;
+
+	while i < N {
+		let (s, a) = splitmix64(state);
+		let (s, b) = splitmix64(s);
+		keys[i] = [a, b];
+		state = s;
+		i += 1;
+	}
+
+	keys
+}
+
+static PIECE_KEYS: [[u64; 2]; MAX_SIZE * MAX_SIZE] = generate(0x6A09E667F3BCC908);
+static CAPTURE_KEYS: [[u64; 2]; 32] = generate(0xBB67AE8584CAA73B);
+const SIDE_KEY: u64 = splitmix64(0x3C6EF372FE94F82B).1;
+
+pub fn piece_key(pos: &Position, piece: Piece) -> u64 {
+	match piece {
+		Piece::Empty => 0,
+		_ => PIECE_KEYS[pos.to_u64(MAX_SIZE) as usize][piece as usize]
+	}
+}
+
+pub fn captures_key(captures: &[usize; 2]) -> u64 {
+	CAPTURE_KEYS[captures[0].min(31)][0] ^ CAPTURE_KEYS[captures[1].min(31)][1]
+}
+
+pub fn side_key(player: Piece) -> u64 {
+	if player == Piece::Min {SIDE_KEY} else {0}
+}
@@ -0,0 +1,94 @@
+use std::sync::{atomic::AtomicBool, Arc};
+
+use backend::analysis::{analyze_game, classify, Evaluation, MoveClass};
+use backend::error::GomokuError;
+use backend::game::Game;
+use backend::opening::OpeningRule;
+use backend::piece::Piece;
+use backend::position::Position;
+use backend::ruleset::RuleKind;
+
+// the engine's choice, and the move it is compared with
+fn engine_move() -> Position {
+	Position::new(9, 9)
+}
+
+fn evaluation(score: f32) -> Evaluation {
+	Evaluation { score, best_move: Some(engine_move()), mate_in: None }
+}
+
+fn class(player: Piece, best: f32, after: f32) -> (f32, MoveClass) {
+	classify(player, Position::new(3, 3), &evaluation(best), &evaluation(after))
+}
+
+#[test]
+fn drops_rate_the_move() {
+	assert_eq!(class(Piece::Max, 10.0, 8.0), (2.0, MoveClass::Best));
+	assert_eq!(class(Piece::Max, 10.0, 4.0), (6.0, MoveClass::Inaccuracy));
+	assert_eq!(class(Piece::Max, 10.0, -10.0), (20.0, MoveClass::Mistake));
+	assert_eq!(class(Piece::Max, 10.0, -60.0), (70.0, MoveClass::Blunder));
+	// a better score than the engine's line is no drop
+	assert_eq!(class(Piece::Max, 10.0, 30.0), (0.0, MoveClass::Best));
+}
+
+#[test]
+fn drops_are_from_the_side_that_moved() {
+	assert_eq!(class(Piece::Min, -10.0, 10.0), (20.0, MoveClass::Mistake));
+	assert_eq!(class(Piece::Min, -10.0, -30.0), (0.0, MoveClass::Best));
+}
+
+#[test]
+fn the_engine_move_is_best_whatever_the_scores() {
+	let best = evaluation(10.0);
+
+	assert_eq!(classify(Piece::Max, engine_move(), &best, &evaluation(-60.0)).1, MoveClass::Best);
+}
+
+#[test]
+fn equal_infinities_are_no_drop() {
+	assert_eq!(class(Piece::Max, f32::INFINITY, f32::INFINITY), (0.0, MoveClass::Best));
+	assert_eq!(class(Piece::Max, -f32::INFINITY, -f32::INFINITY), (0.0, MoveClass::Best));
+	assert_eq!(class(Piece::Min, -f32::INFINITY, -f32::INFINITY), (0.0, MoveClass::Best));
+}
+
+#[test]
+fn letting_a_forced_win_go_is_a_missed_win() {
+	assert_eq!(class(Piece::Max, f32::INFINITY, 10.0).1, MoveClass::MissedWin);
+	assert_eq!(class(Piece::Max, f32::INFINITY, -f32::INFINITY).1, MoveClass::MissedWin);
+	assert_eq!(class(Piece::Min, -f32::INFINITY, 10.0).1, MoveClass::MissedWin);
+}
+
+#[test]
+fn walking_into_a_forced_loss_is_a_blunder() {
+	assert_eq!(class(Piece::Max, 10.0, -f32::INFINITY).1, MoveClass::Blunder);
+}
+
+#[test]
+fn analysis_finds_a_missed_five() {
+	let game = Game::new(19, RuleKind::Freestyle, OpeningRule::Free, None).unwrap();
+	let moves: Vec<Position> = [(5, 9), (5, 0), (6, 9), (6, 0), (7, 9), (7, 0), (8, 9), (8, 0), (0, 18)].iter()
+		.map(|(x, y)| Position::new(*x, *y))
+		.collect();
+
+	let analysis = analyze_game(game, &moves, 2, None, &Arc::new(AtomicBool::new(false))).unwrap();
+
+	assert_eq!(analysis.len(), moves.len());
+	assert_eq!(analysis[8].class, MoveClass::MissedWin);
+	assert!([Position::new(4, 9), Position::new(9, 9)].contains(&analysis[8].best_move));
+}
+
+#[test]
+fn search_errors_come_through_unchanged() {
+	// fills the board without a five, the last position has no move left to search
+	let rows = ["XOXOX", "XOXOX", "OXOXO", "XOXOX", "OXOXO"];
+	let cells = |stone: char| rows.iter().enumerate()
+		.flat_map(move |(y, row)| row.char_indices().filter(move |(_, c)| *c == stone).map(move |(x, _)| Position::with_size(x, y, 5)))
+		.collect::<Vec<_>>();
+	let (black, white) = (cells('X'), cells('O'));
+	let moves: Vec<Position> = (0..25).map(|i| if i % 2 == 0 {black[i / 2]} else {white[i / 2]}).collect();
+
+	let game = Game::new(5, RuleKind::Freestyle, OpeningRule::Free, None).unwrap();
+	let res = analyze_game(game, &moves, 1, None, &Arc::new(AtomicBool::new(false)));
+
+	assert_eq!(res.unwrap_err(), GomokuError::GameOver);
+}
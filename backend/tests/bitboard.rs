@@ -0,0 +1,241 @@
+use backend::bitboard::BitBoard;
+use backend::board::{Board, GameBoard};
+use backend::piece::{Piece, PieceWrap};
+use backend::position::Position;
+
+const DIRECTIONS: [[i32; 2]; 4] = [[1, 0], [0, 1], [1, 1], [-1, 1]];
+
+struct Rng(u64);
+
+impl Rng {
+	fn next(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+}
+
+fn run_length(board: &Board, start: Position, direction: [i32; 2], player: Piece) -> usize {
+	let mut pos = start;
+	let mut length = 0;
+
+	while board[&pos] == player {
+		length += 1;
+		if pos.relocate(direction[0], direction[1]).is_err() {
+			break;
+		}
+	}
+
+	length
+}
+
+fn naive_fives(board: &Board, player: Piece) -> Vec<Position> {
+	board.into_iter().filter(|pos| {
+		DIRECTIONS.iter().any(|d| run_length(board, *pos, *d, player) >= 5)
+	}).collect()
+}
+
+fn naive_open_fours(board: &Board, player: Piece) -> Vec<Position> {
+	board.into_iter().filter(|pos| {
+		DIRECTIONS.iter().any(|d| {
+			let mut before = *pos;
+			let mut after = *pos;
+
+			run_length(board, *pos, *d, player) >= 4 &&
+				before.relocate(-d[0], -d[1]).is_ok() && board[&before].is_empty() &&
+				after.relocate_n(d[0], d[1], 4).is_ok() && board[&after].is_empty()
+		})
+	}).collect()
+}
+
+fn sorted(mut positions: Vec<Position>) -> Vec<(usize, usize)> {
+	positions.sort_by_key(|p| (p.y, p.x));
+	positions.iter().map(|p| (p.x, p.y)).collect()
+}
+
+// Plays a dense random game in the centre of the board so that captures,
+// long runs and edge cases around the guard column all show up.
+fn random_game(seed: u64, moves: usize, area: usize) -> Vec<(Position, Piece)> {
+	let mut rng = Rng(seed);
+	let mut board = Board::new();
+	let mut game = Vec::new();
+	let offset = (19 - area) / 2;
+	let mut player = Piece::Max;
+
+	while game.len() < moves {
+		let pos = Position::new(
+			offset + (rng.next() % area as u64) as usize,
+			offset + (rng.next() % area as u64) as usize
+		);
+
+		if board[&pos].is_piece() {
+			continue;
+		}
+
+		board.set_move(pos, player, None);
+		game.push((pos, player));
+		player = player.get_opposite();
+	}
+
+	game
+}
+
+fn assert_same(board: &Board, other: &impl GameBoard) {
+	assert_eq!(board.size(), other.size());
+	for pos in board {
+		assert_eq!(board[&pos], other[&pos], "cell {} differs", pos);
+	}
+	assert_eq!(board.hash(), other.hash());
+}
+
+// One move through the shared API: the capture map, and the stones it took.
+fn play(board: &mut impl GameBoard, pos: Position, player: Piece) -> (u8, usize) {
+	(board.get_captures(pos, player), board.set_move(pos, player, None))
+}
+
+#[test]
+fn moves_and_captures_match_board() {
+	for seed in 1..40 {
+		let mut board = Board::new();
+		let mut bitboard = BitBoard::new();
+
+		for (pos, player) in random_game(seed, 60, 9) {
+			assert_eq!(play(&mut board, pos, player), play(&mut bitboard, pos, player));
+			assert_same(&board, &bitboard);
+		}
+	}
+}
+
+// Plays `game` on `board`, then takes it back move by move against the boards seen on the way.
+fn assert_unmake_restores(mut board: impl GameBoard, game: &[(Position, Piece)]) {
+	let mut history = Vec::new();
+	let mut snapshots = Vec::new();
+
+	for (pos, player) in game {
+		let mut snapshot = Board::with_size(board.size());
+
+		for pos in &snapshot.clone() {
+			if board[&pos].is_piece() {
+				snapshot.set_move(pos, board[&pos], Some(0));
+			}
+		}
+
+		snapshots.push(snapshot);
+		history.push(board.make_move(*pos, *player, None));
+	}
+
+	while let Some(undo) = history.pop() {
+		board.unmake_move(&undo);
+		assert_same(&snapshots.pop().unwrap(), &board);
+	}
+}
+
+#[test]
+fn unmake_restores_board() {
+	for seed in 1..20 {
+		let game = random_game(seed, 50, 7);
+
+		assert_unmake_restores(Board::new(), &game);
+		assert_unmake_restores(BitBoard::new(), &game);
+	}
+}
+
+#[test]
+fn put_keeps_the_hash() {
+	let mut board = Board::new();
+	let mut bitboard = BitBoard::new();
+
+	for (pos, piece) in [(Position::new(3, 4), Piece::Max), (Position::new(5, 6), Piece::Min), (Position::new(3, 4), Piece::Empty)] {
+		board.put(&pos, piece);
+		bitboard.put(&pos, piece);
+		assert_same(&board, &bitboard);
+	}
+}
+
+#[test]
+fn positions_belong_to_the_board() {
+	let mut bitboard = BitBoard::with_size(15);
+
+	for x in 10..15 {
+		bitboard.set_move(Position::with_size(x, 14, 15), Piece::Max, Some(0));
+	}
+
+	let fives = bitboard.fives(Piece::Max).positions(bitboard.size());
+
+	assert_eq!(fives, vec![Position::with_size(10, 14, 15)]);
+	assert_eq!(bitboard.to_board().size(), 15);
+	assert_same(&bitboard.to_board(), &bitboard);
+
+	// black takes the white pair on (1,0),(2,0)
+	for (x, player) in [(1, Piece::Min), (2, Piece::Min), (3, Piece::Max)] {
+		bitboard.set_move(Position::with_size(x, 0, 15), player, Some(0));
+	}
+
+	let undo = bitboard.make_move(Position::with_size(0, 0, 15), Piece::Max, None);
+
+	assert_eq!(undo.captured, vec![Position::with_size(1, 0, 15), Position::with_size(2, 0, 15)]);
+	// positions compare by cell only
+	assert!(fives.iter().chain(&undo.captured).all(|pos| pos.size() == 15));
+}
+
+#[test]
+fn from_board_round_trips() {
+	for seed in 1..20 {
+		let mut board = Board::new();
+
+		for (pos, player) in random_game(seed, 80, 19) {
+			board.set_move(pos, player, None);
+		}
+
+		let bitboard = BitBoard::from_board(&board);
+
+		assert_same(&board, &bitboard);
+		assert_same(&bitboard.to_board(), &bitboard);
+	}
+}
+
+#[test]
+fn patterns_match_naive_scan() {
+	for seed in 1..60 {
+		let mut board = Board::new();
+		let mut bitboard = BitBoard::new();
+
+		for (pos, player) in random_game(seed, 70, 11) {
+			board.set_move(pos, player, None);
+			bitboard.set_move(pos, player, None);
+
+			for p in [Piece::Max, Piece::Min] {
+				let fives = naive_fives(&board, p);
+
+				assert_eq!(sorted(fives.clone()), sorted(bitboard.fives(p).positions(19)));
+				assert_eq!(!fives.is_empty(), bitboard.has_five(p));
+				assert_eq!(sorted(naive_open_fours(&board, p)), sorted(bitboard.open_fours(p).positions(19)));
+
+				let captures: Vec<Position> = board.into_iter()
+					.filter(|pos| board[pos].is_empty() && Board::get_captures(&board, *pos, p) != 0)
+					.collect();
+
+				assert_eq!(sorted(captures), sorted(bitboard.capture_moves(p).positions(19)));
+			}
+		}
+	}
+}
+
+#[test]
+fn patterns_do_not_wrap_around_edges() {
+	let mut board = Board::new();
+
+	for (x, y) in [(16, 3), (17, 3), (18, 3), (0, 4), (1, 4)] {
+		board.set_move(Position::new(x, y), Piece::Max, Some(0));
+	}
+	for (x, y) in [(2, 10), (1, 11), (0, 12), (18, 12), (17, 13)] {
+		board.set_move(Position::new(x, y), Piece::Min, Some(0));
+	}
+
+	let bitboard = BitBoard::from_board(&board);
+
+	assert!(!bitboard.has_five(Piece::Max));
+	assert!(!bitboard.has_five(Piece::Min));
+	assert!(bitboard.open_fours(Piece::Max).is_empty());
+}
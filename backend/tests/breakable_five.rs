@@ -0,0 +1,122 @@
+use backend::board::Board;
+use backend::heuristic::Heuristic;
+use backend::minimax::GomokuSolver;
+use backend::piece::Piece;
+use backend::position::Position;
+use backend::ruleset::RuleKind;
+
+fn board(stones: &[((usize, usize), Piece)]) -> Board {
+	let mut board = Board::new();
+
+	for ((x, y), player) in stones {
+		board.set_move(Position::new(*x, *y), *player, Some(0));
+	}
+
+	board
+}
+
+// Black five on row 9, columns 5 to 9.
+fn five() -> Vec<((usize, usize), Piece)> {
+	(5..10).map(|x| ((x, 9), Piece::Max)).collect()
+}
+
+// The five plus a black stone below (7,9), flanked by white: white breaks the five on (7,8).
+fn breakable_five() -> Vec<((usize, usize), Piece)> {
+	let mut stones = five();
+
+	stones.push(((7, 10), Piece::Max));
+	stones.push(((7, 11), Piece::Min));
+	stones
+}
+
+fn heuristic(stones: &[((usize, usize), Piece)], captures: [usize; 2], rules: RuleKind) -> Heuristic {
+	Heuristic::from_board(&board(stones), &captures, rules.ruleset())
+}
+
+#[test]
+fn unbreakable_five_wins() {
+	let mut heuristic = heuristic(&five(), [0, 0], RuleKind::FortyTwo);
+
+	assert_eq!(heuristic.get_heuristic(), f32::INFINITY);
+	assert_eq!(heuristic.winner(Piece::Min), Some(Piece::Max));
+	assert_eq!(heuristic.winner(Piece::Max), Some(Piece::Max));
+}
+
+#[test]
+fn five_breakable_by_capture_does_not_win_yet() {
+	let mut heuristic = heuristic(&breakable_five(), [0, 0], RuleKind::FortyTwo);
+
+	assert!(heuristic.get_heuristic().is_finite());
+	assert!(heuristic.get_heuristic() > 0.0);
+	assert_eq!(heuristic.winner(Piece::Min), None);
+}
+
+#[test]
+fn breakable_five_wins_when_left_standing() {
+	let heuristic = heuristic(&breakable_five(), [0, 0], RuleKind::FortyTwo);
+
+	assert_eq!(heuristic.winner(Piece::Max), Some(Piece::Max));
+}
+
+#[test]
+fn pair_blocked_on_both_sides_cannot_break_five() {
+	let mut stones = breakable_five();
+
+	stones.push(((7, 8), Piece::Max));
+
+	let mut heuristic = heuristic(&stones, [0, 0], RuleKind::FortyTwo);
+
+	assert_eq!(heuristic.get_heuristic(), f32::INFINITY);
+	assert_eq!(heuristic.winner(Piece::Min), Some(Piece::Max));
+}
+
+#[test]
+fn five_does_not_win_against_last_capture() {
+	// A black pair far away from the five, open to white.
+	let mut stones = five();
+
+	stones.extend([((3, 3), Piece::Max), ((4, 3), Piece::Max), ((5, 3), Piece::Min)]);
+
+	let mut last_capture = heuristic(&stones, [0, 4], RuleKind::FortyTwo);
+	let mut no_threat = heuristic(&stones, [0, 3], RuleKind::FortyTwo);
+
+	assert!(last_capture.get_heuristic().is_finite());
+	assert_eq!(last_capture.winner(Piece::Min), None);
+	assert_eq!(no_threat.get_heuristic(), f32::INFINITY);
+	assert_eq!(no_threat.winner(Piece::Min), Some(Piece::Max));
+}
+
+#[test]
+fn five_always_wins_without_captures() {
+	let mut heuristic = heuristic(&breakable_five(), [0, 0], RuleKind::Gomoku);
+
+	assert_eq!(heuristic.get_heuristic(), f32::INFINITY);
+	assert_eq!(heuristic.winner(Piece::Min), Some(Piece::Max));
+}
+
+#[test]
+fn search_breaks_the_five() {
+	let mut solver = GomokuSolver::new(board(&breakable_five()), [0, 0], Piece::Min, 2);
+
+	solver.is_hint = Some(true);
+
+	let res = solver.solve().unwrap();
+
+	assert_eq!(res.position, Position::new(7, 8));
+}
+
+#[test]
+fn search_takes_the_last_capture() {
+	let mut stones = five();
+
+	stones.extend([((3, 3), Piece::Max), ((4, 3), Piece::Max), ((5, 3), Piece::Min)]);
+
+	let mut solver = GomokuSolver::new(board(&stones), [0, 4], Piece::Min, 2);
+
+	solver.is_hint = Some(true);
+
+	let res = solver.solve().unwrap();
+
+	assert_eq!(res.position, Position::new(2, 3));
+	assert_eq!(res.score, f32::NEG_INFINITY);
+}
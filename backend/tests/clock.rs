@@ -0,0 +1,96 @@
+use std::time::{Duration, Instant};
+
+use backend::clock::{Clock, TimeControl};
+use backend::piece::Piece;
+
+fn ms(ms: u64) -> Duration {
+	Duration::from_millis(ms)
+}
+
+// A clock with black's running since `start`.
+fn running(control: TimeControl, start: Instant) -> Clock {
+	let mut clock = Clock::new(control);
+
+	clock.start(Piece::Max, start);
+	clock
+}
+
+#[test]
+fn fischer_adds_the_increment() {
+	let start = Instant::now();
+	let mut clock = running(TimeControl::Fischer { initial_ms: 1000, increment_ms: 100 }, start);
+
+	assert!(clock.press(Piece::Max, start + ms(300)));
+	assert_eq!(clock.remaining(Piece::Max, start + ms(500)), ms(800));
+	// white's clock runs now
+	assert_eq!(clock.remaining(Piece::Min, start + ms(500)), ms(800));
+}
+
+#[test]
+fn bronstein_gives_back_at_most_the_time_used() {
+	let start = Instant::now();
+	let mut clock = running(TimeControl::Bronstein { initial_ms: 1000, delay_ms: 200 }, start);
+
+	assert!(clock.press(Piece::Max, start + ms(150)));
+	assert_eq!(clock.remaining(Piece::Max, start + ms(150)), ms(1000));
+
+	assert!(clock.press(Piece::Min, start + ms(650)));
+	assert_eq!(clock.remaining(Piece::Min, start + ms(650)), ms(700));
+}
+
+#[test]
+fn per_move_time_starts_over_every_move() {
+	let start = Instant::now();
+	let mut clock = running(TimeControl::PerMove { move_ms: 1000 }, start);
+
+	assert!(clock.press(Piece::Max, start + ms(900)));
+	assert_eq!(clock.remaining(Piece::Max, start + ms(900)), ms(1000));
+}
+
+#[test]
+fn pressing_too_late_flags() {
+	let start = Instant::now();
+	let mut clock = running(TimeControl::Fischer { initial_ms: 1000, increment_ms: 100 }, start);
+
+	assert!(!clock.press(Piece::Max, start + ms(1500)));
+	assert_eq!(clock.flagged(start + ms(1500)), Some(Piece::Max));
+	assert_eq!(clock.remaining(Piece::Max, start + ms(1500)), Duration::ZERO);
+	// both clocks stay stopped
+	assert!(!clock.press(Piece::Min, start + ms(1600)));
+	assert_eq!(clock.remaining(Piece::Min, start + ms(5000)), ms(1000));
+}
+
+#[test]
+fn the_running_clock_flags_without_a_press() {
+	let start = Instant::now();
+	let clock = running(TimeControl::Fischer { initial_ms: 1000, increment_ms: 0 }, start);
+
+	assert_eq!(clock.flagged(start + ms(999)), None);
+	assert_eq!(clock.flagged(start + ms(1000)), Some(Piece::Max));
+	assert_eq!(clock.snapshot(start + ms(1000)).flagged, Some(Piece::Max));
+}
+
+#[test]
+fn a_checked_flag_keeps_the_time_from_coming_back() {
+	let start = Instant::now();
+	let mut clock = running(TimeControl::Fischer { initial_ms: 1000, increment_ms: 0 }, start);
+
+	assert_eq!(clock.check_flag(start + ms(500)), None);
+	assert_eq!(clock.check_flag(start + ms(1200)), Some(Piece::Max));
+
+	// taking a move back restarts the clock of the side to move, but not after a flag
+	clock.start(Piece::Max, start + ms(1300));
+
+	assert_eq!(clock.remaining(Piece::Max, start + ms(1300)), Duration::ZERO);
+	assert_eq!(clock.flagged(start + ms(1300)), Some(Piece::Max));
+	assert_eq!(clock.snapshot(start + ms(1300)).running, None);
+}
+
+#[test]
+fn search_budgets_leave_time_on_the_clock() {
+	let start = Instant::now();
+	let clock = running(TimeControl::PerMove { move_ms: 1000 }, start);
+
+	assert_eq!(clock.search_budget(Piece::Max, start), ms(950));
+	assert!(clock.search_budget(Piece::Max, start + ms(990)) >= ms(1));
+}
@@ -0,0 +1,60 @@
+use backend::board::Board;
+use backend::error::GomokuError;
+use backend::game::Game;
+use backend::opening::OpeningRule;
+use backend::piece::Piece;
+use backend::position::Position;
+use backend::ruleset::RuleKind;
+
+// Black takes the white pair on (6, 5) and (7, 5) with its last move.
+fn captured_game() -> Game {
+	let mut game = Game::new(19, RuleKind::FortyTwo, OpeningRule::Free, None).unwrap();
+
+	for (x, y) in [(5, 5), (6, 5), (0, 0), (7, 5), (8, 5)] {
+		game.play(Position::new(x, y)).unwrap();
+	}
+
+	game
+}
+
+fn pair_taken(board: &Board) -> bool {
+	board[&Position::new(6, 5)] == Piece::Empty && board[&Position::new(7, 5)] == Piece::Empty
+}
+
+#[test]
+fn undo_puts_a_captured_pair_back() {
+	let mut game = captured_game();
+
+	assert!(pair_taken(&game.board));
+	assert_eq!(game.captures, [1, 0]);
+
+	let hash = game.board.hash();
+
+	game.undo().unwrap();
+
+	assert_eq!(game.board[&Position::new(6, 5)], Piece::Min);
+	assert_eq!(game.board[&Position::new(7, 5)], Piece::Min);
+	assert_eq!(game.board[&Position::new(8, 5)], Piece::Empty);
+	assert_eq!(game.captures, [0, 0]);
+	assert_eq!(game.player, Piece::Max);
+
+	game.redo().unwrap();
+
+	assert!(pair_taken(&game.board));
+	assert_eq!(game.captures, [1, 0]);
+	assert_eq!(game.player, Piece::Min);
+	assert_eq!(game.board.hash(), hash);
+	assert_eq!(game.redo().unwrap_err(), GomokuError::NothingToRedo);
+}
+
+#[test]
+fn a_new_move_after_undo_drops_the_redo() {
+	let mut game = captured_game();
+
+	game.undo().unwrap();
+	game.play(Position::new(9, 9)).unwrap();
+
+	assert_eq!(game.redo_count(), 0);
+	assert_eq!(game.captures, [0, 0]);
+	assert_eq!(game.board[&Position::new(6, 5)], Piece::Min);
+}
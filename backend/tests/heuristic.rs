@@ -0,0 +1,306 @@
+use backend::board::Board;
+use backend::heuristic::{EvalWeights, Heuristic, Line, LineScoring};
+use backend::piece::Piece;
+use backend::position::Position;
+use backend::ruleset::RuleKind;
+
+fn capture_scores(captures: [usize; 2], capture_win: Option<usize>) -> [f32; 2] {
+	let mut heuristic = Heuristic::from_board(&Board::new(), &captures, RuleKind::FortyTwo.ruleset());
+
+	heuristic.capture_win = capture_win;
+	heuristic.explain(None).unwrap().captures
+}
+
+// Stones on row 9, by column.
+fn row(stones: &[(usize, Piece)]) -> Heuristic {
+	let mut board = Board::new();
+
+	for (x, player) in stones {
+		board.set_move(Position::new(*x, 9), *player, Some(0));
+	}
+
+	let mut heuristic = Heuristic::from_board(&board, &[0, 0], RuleKind::Freestyle.ruleset());
+
+	heuristic.get_heuristic();
+	heuristic
+}
+
+fn black(xs: &[usize]) -> Vec<(usize, Piece)> {
+	xs.iter().map(|x| (*x, Piece::Max)).collect()
+}
+
+// Gapped shapes along the row, as (first stone, score).
+fn gapped(stones: &[(usize, Piece)]) -> Vec<(usize, f32)> {
+	row(stones).explain(None).unwrap().gapped.iter()
+		.filter(|term| term.direction == 0)
+		.map(|term| (term.start.x, term.contribution))
+		.collect()
+}
+
+// The score of the contiguous line of `length` along the row.
+fn line(stones: &[(usize, Piece)], length: usize) -> f32 {
+	row(stones).explain(None).unwrap().lines.iter()
+		.find(|term| term.line.direction == 0 && term.line.length == length)
+		.map(|term| term.contribution)
+		.unwrap()
+}
+
+#[test]
+fn no_captures_score_nothing_whatever_the_capture_win() {
+	for win in 1..=8 {
+		assert_eq!(capture_scores([0, 0], Some(win)), [0.0, 0.0], "capture win {}", win);
+	}
+}
+
+#[test]
+fn every_capture_scores_at_least_the_first_entry() {
+	// [0, 8, 16, 32, 64] by default
+	assert_eq!(capture_scores([1, 2], Some(3)), [32.0, 64.0]);
+	assert_eq!(capture_scores([1, 4], Some(5)), [8.0, 64.0]);
+	assert_eq!(capture_scores([1, 3], Some(7)), [8.0, 8.0]);
+	assert_eq!(capture_scores([5, 6], Some(7)), [32.0, 64.0]);
+	assert_eq!(capture_scores([3, 7], Some(7)), [8.0, f32::INFINITY]);
+}
+
+#[test]
+fn captures_without_a_capture_win_count_as_material() {
+	assert_eq!(capture_scores([0, 3], None), [0.0, 24.0]);
+}
+
+#[test]
+fn open_split_three_scores_like_an_open_three() {
+	let open_three = line(&black(&[5, 6, 7]), 3);
+
+	assert!(open_three > 0.0);
+	assert_eq!(gapped(&black(&[5, 7, 8])), vec![(5, open_three)]);
+	assert_eq!(gapped(&black(&[5, 6, 8])), vec![(5, open_three)]);
+}
+
+#[test]
+fn closed_split_three_scores_like_a_closed_three() {
+	let mut closed = black(&[5, 6, 7]);
+	let mut split = black(&[5, 7, 8]);
+
+	closed.push((4, Piece::Min));
+	split.push((9, Piece::Min));
+
+	let closed_three = line(&closed, 3);
+
+	assert!(closed_three < line(&black(&[5, 6, 7]), 3));
+	assert_eq!(gapped(&split), vec![(5, closed_three)]);
+
+	// against the edge
+	assert_eq!(gapped(&black(&[0, 2, 3])), vec![(0, closed_three)]);
+}
+
+#[test]
+fn split_four_scores_like_a_closed_four() {
+	let mut closed = black(&[5, 6, 7, 8]);
+
+	closed.push((4, Piece::Min));
+
+	let closed_four = line(&closed, 4);
+
+	for xs in [[5, 7, 8, 9], [5, 6, 8, 9], [5, 6, 7, 9]] {
+		assert_eq!(gapped(&black(&xs)), vec![(5, closed_four)], "{:?}", xs);
+	}
+}
+
+#[test]
+fn shapes_need_one_empty_gap_and_three_stones() {
+	assert_eq!(gapped(&black(&[5, 7])), vec![]);
+	assert_eq!(gapped(&black(&[5, 8, 9])), vec![]);
+	assert_eq!(gapped(&[(5, Piece::Max), (6, Piece::Min), (7, Piece::Max), (8, Piece::Max)]), vec![]);
+	assert_eq!(gapped(&black(&[5, 6, 8, 9, 10])), vec![]);
+}
+
+#[test]
+fn overlapping_shapes_count_once() {
+	let split_four = gapped(&black(&[5, 6, 8, 9]));
+
+	assert_eq!(gapped(&black(&[5, 6, 8, 9, 11])), split_four);
+	assert_eq!(gapped(&black(&[5, 7, 8, 10])).len(), 1);
+}
+
+// What a black move on `x` changes along the row, as move ordering scores it.
+fn row_delta(stones: &[(usize, Piece)], x: usize) -> f32 {
+	row(stones).explain(Some((Position::new(x, 9), Piece::Max))).unwrap().candidate.unwrap().directions[0]
+}
+
+#[test]
+fn move_ordering_sees_gapped_shapes() {
+	let open_three = line(&black(&[5, 6, 7]), 3);
+
+	// `X_X` to `X_XX`, against the same move next to a lone stone
+	assert_eq!(row_delta(&black(&[5, 7]), 8) - row_delta(&black(&[7]), 8), open_three);
+
+	// filling the gap of `X_XX` trades the split three for an open four
+	let open_four = line(&black(&[5, 6, 7, 8]), 4);
+	let fill = row_delta(&black(&[5, 7, 8]), 6);
+
+	assert_eq!(fill, open_four - line(&black(&[7, 8]), 2) - open_three);
+}
+
+// The line scores from before the weights could be changed, by blocked ends.
+const BUILTIN_LINES: [[f32; 6]; 3] = [
+	[1.0, 2.0, 4.0, 16.0, 64.0, f32::INFINITY],
+	[1.0, 2.0, 0.0, 8.0, 16.0, f32::INFINITY],
+	[0.0, 0.0, 0.0, 0.0, 0.0, f32::INFINITY],
+];
+
+#[test]
+fn default_weights_reproduce_the_builtin_scores() {
+	let weights = EvalWeights::default();
+	let scoring = LineScoring { rules: RuleKind::FortyTwo.ruleset(), weights: &weights };
+
+	for (blocks, scores) in [(0, BUILTIN_LINES[0]), (1, BUILTIN_LINES[1]), (2, BUILTIN_LINES[1]), (3, BUILTIN_LINES[2])] {
+		for length in 1..=7 {
+			let builtin = scores[length.min(5)];
+
+			assert_eq!(Line::calculate(blocks, length, Piece::Max, scoring), builtin, "blocks {} length {}", blocks, length);
+			assert_eq!(Line::calculate(blocks, length, Piece::Min, scoring), -builtin, "blocks {} length {}", blocks, length);
+		}
+	}
+
+	assert_eq!(weights.captures, [0.0, 8.0, 16.0, 32.0, 64.0]);
+	assert_eq!(weights.centrality, 1.0);
+	assert_eq!(weights.move_centrality, 0.25);
+}
+
+// xorshift, so the move sequences are the same on every run
+fn next_random(state: &mut u64) -> u64 {
+	*state ^= *state << 13;
+	*state ^= *state >> 7;
+	*state ^= *state << 17;
+	*state
+}
+
+// The lines as their owner, ends and score, and for every stone the line it is on in each direction.
+fn line_state(heuristic: &Heuristic) -> (Vec<String>, Vec<String>) {
+	let describe = |line: &Line| format!("{:?} {} {} {} {} {}", line.player, line.start, line.end, line.direction, line.block_pos, line.score);
+	let mut lines: Vec<String> = heuristic.lines.values().map(describe).collect();
+	let mut positions = Vec::new();
+
+	for pos in &heuristic.board {
+		if heuristic.board[&pos] == Piece::Empty {
+			continue;
+		}
+
+		for direction in 0..4 {
+			let line = heuristic.line_pos.get(&pos).and_then(|ids| heuristic.lines.get(&ids[direction]));
+
+			positions.push(format!("{} {}: {:?}", pos, direction, line.map(describe)));
+		}
+	}
+
+	lines.sort();
+	(lines, positions)
+}
+
+// The same position evaluated from scratch.
+fn assert_matches_fresh(heuristic: &mut Heuristic, context: &str) {
+	let mut fresh = Heuristic::from_board(&heuristic.board, &heuristic.captures, RuleKind::FortyTwo.ruleset());
+	let (score, expected) = (heuristic.get_heuristic(), fresh.get_heuristic());
+
+	assert_eq!(line_state(heuristic), line_state(&fresh), "{}", context);
+	// the lines are summed in hash map order
+	assert!(score == expected || (score - expected).abs() < 1e-3, "{}: {} against {}", context, score, expected);
+}
+
+#[test]
+fn incremental_updates_match_a_fresh_evaluation() {
+	let rules = RuleKind::FortyTwo.ruleset();
+	let mut captures = 0;
+
+	for seed in 1..=20u64 {
+		let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+		let mut heuristic = Heuristic::from_board(&Board::new(), &[0, 0], rules);
+		let mut undos = Vec::new();
+		let mut player = Piece::Max;
+
+		heuristic.get_heuristic();
+
+		// a crowded 6x6 corner of the centre, where pairs get captured
+		for ply in 0..30 {
+			let pos = Position::new(7 + next_random(&mut state) as usize % 6, 7 + next_random(&mut state) as usize % 6);
+
+			if heuristic.board[&pos] != Piece::Empty {
+				continue;
+			}
+
+			let capture_map = rules.capture_map(&heuristic.board, pos, player);
+			let undo = heuristic.make_move(pos, player, Some(capture_map));
+
+			captures += undo.capture_count();
+			undos.push(undo);
+			assert_matches_fresh(&mut heuristic, &format!("seed {} ply {} {}", seed, ply, pos));
+			player = if player == Piece::Max {Piece::Min} else {Piece::Max};
+		}
+
+		while let Some(undo) = undos.pop() {
+			heuristic.unmake_move(undo);
+			assert_matches_fresh(&mut heuristic, &format!("seed {} taking back to {} moves", seed, undos.len()));
+		}
+
+		assert!(heuristic.lines.is_empty());
+	}
+
+	assert!(captures > 0);
+}
+
+fn sorted_gapped(heuristic: &Heuristic) -> Vec<String> {
+	let mut gapped: Vec<String> = heuristic.gapped.as_ref().unwrap().iter()
+		.map(|((start, direction), score)| format!("{} {} {}", start, direction, score))
+		.collect();
+
+	gapped.sort();
+	gapped
+}
+
+#[test]
+fn gapped_shapes_follow_moves_and_captures() {
+	let rules = RuleKind::FortyTwo.ruleset();
+	let weights = EvalWeights { open: [1.0, 3.0, 5.0, 20.0, 70.0], half_open: [1.0, 2.0, 1.0, 9.0, 17.0], ..EvalWeights::default() };
+	let (mut shapes, mut captures) = (0, 0);
+
+	for seed in 1..=20u64 {
+		let mut state = seed.wrapping_mul(0x2545_F491_4F6C_DD1D);
+		let mut heuristic = Heuristic::with_weights(&Board::new(), &[0, 0], rules, weights);
+		let mut undos = Vec::new();
+		let mut player = Piece::Max;
+
+		heuristic.get_heuristic();
+
+		for _ in 0..40 {
+			let pos = Position::new(6 + next_random(&mut state) as usize % 7, 6 + next_random(&mut state) as usize % 7);
+
+			if heuristic.board[&pos] != Piece::Empty {
+				continue;
+			}
+
+			let capture_map = rules.capture_map(&heuristic.board, pos, player);
+
+			undos.push(heuristic.make_move(pos, player, Some(capture_map)));
+			captures += undos.last().unwrap().capture_count();
+
+			let mut fresh = Heuristic::with_weights(&heuristic.board, &heuristic.captures, rules, weights);
+			let (score, expected) = (heuristic.get_heuristic(), fresh.get_heuristic());
+
+			assert_eq!(sorted_gapped(&heuristic), sorted_gapped(&fresh), "seed {} after {}", seed, pos);
+			assert!(score == expected || (score - expected).abs() < 1e-3, "seed {} after {}: {} against {}", seed, pos, score, expected);
+			shapes += sorted_gapped(&heuristic).len();
+			player = if player == Piece::Max {Piece::Min} else {Piece::Max};
+		}
+
+		while let Some(undo) = undos.pop() {
+			heuristic.unmake_move(undo);
+
+			let mut fresh = Heuristic::with_weights(&heuristic.board, &heuristic.captures, rules, weights);
+
+			fresh.get_heuristic();
+			assert_eq!(sorted_gapped(&heuristic), sorted_gapped(&fresh), "seed {} taking back to {} moves", seed, undos.len());
+		}
+	}
+
+	assert!(shapes > 0 && captures > 0, "{} shapes, {} captures", shapes, captures);
+}
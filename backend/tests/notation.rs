@@ -0,0 +1,70 @@
+use backend::error::GomokuError;
+use backend::notation::{Notation, Record};
+use backend::opening::OpeningRule;
+use backend::piece::{Piece, PieceWrap};
+use backend::position::Position;
+use backend::ruleset::RuleKind;
+
+// Black captures the white pair on row 5, then white plays again where the pair stood.
+fn capture_record() -> Record {
+	Record {
+		size: 19,
+		moves: [(5, 5), (6, 5), (0, 0), (7, 5), (8, 5), (6, 5)].iter()
+			.map(|(x, y)| Position::new(*x, *y))
+			.collect(),
+	}
+}
+
+#[test]
+fn notations_round_trip() {
+	let record = capture_record();
+
+	for notation in [Notation::Psq, Notation::Sgf, Notation::Moves] {
+		let text = notation.write(&record);
+
+		assert_eq!(notation.read(&text, 19), Ok(record.clone()), "{:?}: {}", notation, text);
+	}
+}
+
+#[test]
+fn replay_takes_captures() {
+	let game = capture_record().replay(RuleKind::FortyTwo, OpeningRule::Free, None).unwrap();
+
+	assert_eq!(game.captures, [1, 0]);
+	assert_eq!(game.board[&Position::new(6, 5)], Piece::Min);
+	assert!(!game.board[&Position::new(7, 5)].is_piece());
+	assert_eq!(game.player, Piece::Max);
+}
+
+#[test]
+fn replay_rejects_occupied_squares_without_captures() {
+	let res = capture_record().replay(RuleKind::Gomoku, OpeningRule::Free, None);
+
+	assert!(matches!(res, Err(GomokuError::InvalidNotation(_))));
+}
+
+#[test]
+fn reads_gomocup_psq() {
+	let text = "Piskvorky 20x20, 11:11, 0\n10,10,1500\n11,11,1230\n10,11,980\n-1\npbrain-a.exe\npbrain-b.exe\n";
+	let record = Notation::Psq.read(text, 19).unwrap();
+
+	assert_eq!(record.size, 20);
+	assert_eq!(record.moves, vec![Position::new(9, 9), Position::new(10, 10), Position::new(9, 10)]);
+}
+
+#[test]
+fn reads_sgf_main_line() {
+	let text = "(;FF[4]GM[4]SZ[15]PB[black]C[a \\] comment];B[hh];W[ih](;B[ii];W[jj])(;B[gg]))";
+	let record = Notation::Sgf.read(text, 19).unwrap();
+
+	assert_eq!(record.size, 15);
+	assert_eq!(record.moves, vec![Position::new(7, 7), Position::new(8, 7), Position::new(8, 8), Position::new(9, 9)]);
+}
+
+#[test]
+fn reads_lowercase_moves() {
+	let record = Notation::Moves.read("j10 k11, J9", 19).unwrap();
+
+	assert_eq!(record.moves, vec![Position::new(10, 9), Position::new(11, 10), Position::new(9, 9)]);
+	assert!(Notation::Moves.read("j10 z3", 19).is_err());
+}
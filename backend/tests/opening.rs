@@ -0,0 +1,137 @@
+use backend::board::Board;
+use backend::error::GomokuError;
+use backend::game::Game;
+use backend::heuristic::EvalWeights;
+use backend::opening::{self, OpeningContext, OpeningDecision, OpeningRule};
+use backend::piece::{Piece, PieceWrap};
+use backend::position::Position;
+use backend::ruleset::RuleKind;
+
+fn context() -> OpeningContext {
+	OpeningContext { rules: RuleKind::default().ruleset(), capture_win: None, weights: EvalWeights::default() }
+}
+
+fn board(stones: &[((usize, usize), Piece)]) -> Board {
+	let mut board = Board::new();
+
+	for ((x, y), player) in stones {
+		board.set_move(Position::new(*x, *y), *player, Some(0));
+	}
+
+	board
+}
+
+fn distance(a: Position, b: Position) -> usize {
+	a.x.abs_diff(b.x).max(a.y.abs_diff(b.y))
+}
+
+#[test]
+fn pro_openings_start_in_the_centre() {
+	let empty = Board::new();
+
+	for rule in [OpeningRule::Pro, OpeningRule::LongPro] {
+		assert!(opening::is_allowed(rule, &empty, Position::new(9, 9), Piece::Max));
+		assert!(!opening::is_allowed(rule, &empty, Position::new(9, 10), Piece::Max));
+		assert_eq!(opening::candidates(rule, &empty, Piece::Max), vec![Position::new(9, 9)]);
+	}
+
+	assert!(opening::is_allowed(OpeningRule::Free, &empty, Position::new(0, 0), Piece::Max));
+}
+
+#[test]
+fn black_keeps_its_distance_on_the_second_stone() {
+	let board = board(&[((9, 9), Piece::Max), ((10, 10), Piece::Min)]);
+
+	for (rule, min_distance) in [(OpeningRule::Pro, 3), (OpeningRule::LongPro, 4)] {
+		for x in 9..19 {
+			let pos = Position::new(x, 9);
+
+			assert_eq!(opening::is_allowed(rule, &board, pos, Piece::Max), x - 9 >= min_distance, "{:?} {}", rule, pos);
+			// white and later stones are not restricted
+			assert!(opening::is_allowed(rule, &board, pos, Piece::Min));
+		}
+
+		let candidates = opening::candidates(rule, &board, Piece::Max);
+
+		assert!(!candidates.is_empty());
+		assert!(candidates.iter().all(|pos| distance(*pos, Position::new(9, 9)) == min_distance), "{:?}", rule);
+	}
+}
+
+#[test]
+fn games_reject_moves_the_opening_forbids() {
+	let mut game = Game::new(19, RuleKind::default(), OpeningRule::Pro, None).unwrap();
+
+	assert_eq!(game.play(Position::new(3, 3)).unwrap_err(), GomokuError::ForbiddenMove(Position::new(3, 3)));
+	game.play(Position::new(9, 9)).unwrap();
+	game.play(Position::new(10, 10)).unwrap();
+	assert_eq!(game.play(Position::new(11, 9)).unwrap_err(), GomokuError::ForbiddenMove(Position::new(11, 9)));
+	game.play(Position::new(12, 9)).unwrap();
+}
+
+#[test]
+fn swap_openings_propose_three_stones_near_the_centre() {
+	for rule in [OpeningRule::Swap, OpeningRule::Swap2] {
+		let stones = opening::propose(rule, &Board::new(), &context()).unwrap();
+
+		assert_eq!(stones.iter().map(|stone| stone.player).collect::<Vec<_>>(), vec![Piece::Max, Piece::Min, Piece::Max]);
+		assert_eq!(stones[0].position, Position::new(9, 9));
+		assert!(stones.iter().all(|stone| distance(stone.position, Position::new(9, 9)) <= 2), "{:?}", stones);
+	}
+
+	assert!(matches!(opening::propose(OpeningRule::Pro, &Board::new(), &context()), Err(GomokuError::InvalidOpening(_))));
+	assert!(matches!(
+		opening::propose(OpeningRule::Swap, &board(&[((9, 9), Piece::Max)]), &context()),
+		Err(GomokuError::InvalidOpening(_))
+	));
+}
+
+#[test]
+fn the_chooser_takes_the_stronger_colour() {
+	let black_ahead = board(&[((9, 9), Piece::Max), ((10, 9), Piece::Max), ((0, 0), Piece::Min)]);
+	let white_ahead = board(&[((9, 9), Piece::Min), ((10, 9), Piece::Min), ((0, 0), Piece::Max)]);
+
+	for rule in [OpeningRule::Swap, OpeningRule::Swap2] {
+		assert_eq!(opening::choose(rule, &black_ahead, &context(), 2).unwrap(), OpeningDecision::Black, "{:?}", rule);
+		assert_eq!(opening::choose(rule, &white_ahead, &context(), 2).unwrap(), OpeningDecision::White, "{:?}", rule);
+	}
+}
+
+#[test]
+fn swap2_adds_two_stones_to_a_balanced_opening() {
+	// within the balance margin three plies deep
+	let mut board = board(&[((9, 9), Piece::Max), ((10, 10), Piece::Min), ((11, 9), Piece::Max)]);
+
+	let OpeningDecision::Place { stones: added } = opening::choose(OpeningRule::Swap2, &board, &context(), 3).unwrap() else {
+		panic!("a balanced opening gets two more stones");
+	};
+
+	assert_eq!(added.iter().map(|stone| stone.player).collect::<Vec<_>>(), vec![Piece::Max, Piece::Min]);
+
+	for stone in &added {
+		assert!(board[&stone.position].is_empty());
+		assert!(distance(stone.position, Position::new(9, 9)) <= 2);
+		board.set_move(stone.position, stone.player, Some(0));
+	}
+
+	// five stones: the first player now has to pick a colour
+	assert!(matches!(opening::choose(OpeningRule::Swap2, &board, &context(), 2).unwrap(), OpeningDecision::Black | OpeningDecision::White));
+	assert!(matches!(opening::choose(OpeningRule::Swap, &board, &context(), 2), Err(GomokuError::InvalidOpening(_))));
+}
+
+#[test]
+fn choices_use_the_game_weights() {
+	let board = board(&[((9, 9), Piece::Max), ((10, 9), Piece::Max), ((0, 0), Piece::Min)]);
+	let mut context = context();
+
+	// with every shape counting against its owner, black's two stones are a burden
+	let default = EvalWeights::default();
+	context.weights = EvalWeights {
+		open: default.open.map(|w| -w),
+		half_open: default.half_open.map(|w| -w),
+		centrality: -default.centrality,
+		..default
+	};
+	assert_eq!(opening::choose(OpeningRule::Swap, &board, &self::context(), 1).unwrap(), OpeningDecision::Black);
+	assert_eq!(opening::choose(OpeningRule::Swap, &board, &context, 1).unwrap(), OpeningDecision::White);
+}
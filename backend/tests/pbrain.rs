@@ -0,0 +1,87 @@
+use std::io;
+
+use backend::board::Board;
+use backend::pbrain::{self, Command, Info};
+use backend::position::Position;
+use backend::ruleset::RuleKind;
+
+fn parse(line: &str) -> Result<Command, String> {
+	pbrain::parse_command(&Board::with_size(15), line)
+}
+
+fn lines(text: &[&str]) -> impl Iterator<Item = io::Result<String>> {
+	text.iter().map(|line| Ok(line.to_string())).collect::<Vec<_>>().into_iter()
+}
+
+#[test]
+fn game_commands() {
+	assert_eq!(parse("START 19"), Ok(Command::Start(19)));
+	assert_eq!(parse("start 15\r"), Ok(Command::Start(15)));
+	assert_eq!(parse("RESTART"), Ok(Command::Restart));
+	assert_eq!(parse("BEGIN"), Ok(Command::Begin));
+	assert_eq!(parse("BOARD"), Ok(Command::Board));
+	assert_eq!(parse("ABOUT"), Ok(Command::About));
+	assert_eq!(parse("END"), Ok(Command::End));
+	assert_eq!(parse("  "), Ok(Command::Empty));
+	assert_eq!(parse("SWAP2BOARD"), Ok(Command::Unknown("SWAP2BOARD".to_string())));
+}
+
+#[test]
+fn start_needs_a_supported_size() {
+	assert!(parse("START").is_err());
+	assert!(parse("START big").is_err());
+	assert!(parse("START 100").is_err());
+}
+
+#[test]
+fn moves_are_checked_against_the_board() {
+	let size = 15;
+
+	assert_eq!(parse("TURN 7,8"), Ok(Command::Turn(Position::with_size(7, 8, size))));
+	assert_eq!(parse("TURN 0, 14"), Ok(Command::Turn(Position::with_size(0, 14, size))));
+	assert_eq!(parse("TAKEBACK 3,4"), Ok(Command::Takeback(Position::with_size(3, 4, size))));
+
+	for line in ["TURN", "TURN 7", "TURN 7;8", "TURN a,b", "TURN -1,3", "TURN 7,8,9", "TAKEBACK x,y"] {
+		assert_eq!(parse(line), Err(format!("invalid coordinates '{}'", line.split_once(' ').map_or("", |(_, args)| args))), "{}", line);
+	}
+
+	// out of range on 15x15, fine on 19x19
+	assert!(parse("TURN 15,3").is_err());
+	assert!(parse("TURN 3,15").is_err());
+	assert_eq!(pbrain::parse_command(&Board::new(), "TURN 15,3"), Ok(Command::Turn(Position::new(15, 3))));
+}
+
+#[test]
+fn info_settings() {
+	assert_eq!(parse("INFO timeout_turn 1000"), Ok(Command::Info(Info::TimeoutTurn(1000))));
+	assert_eq!(parse("INFO time_left 90000"), Ok(Command::Info(Info::TimeLeft(90000))));
+	assert_eq!(parse("INFO timeout_match 180000"), Ok(Command::Info(Info::Ignored)));
+	assert_eq!(parse("INFO time_left soon"), Ok(Command::Info(Info::Ignored)));
+	assert_eq!(parse("INFO folder /tmp"), Ok(Command::Info(Info::Ignored)));
+}
+
+#[test]
+fn rule_bits() {
+	// bit 0 exactly five, bit 1 continuous game, bit 2 renju, bit 3 caro
+	for (bits, rules) in [(0, RuleKind::Freestyle), (1, RuleKind::Gomoku), (2, RuleKind::Freestyle), (3, RuleKind::Gomoku), (4, RuleKind::Renju), (5, RuleKind::Renju), (8, RuleKind::Freestyle)] {
+		assert_eq!(parse(&format!("INFO rule {}", bits)), Ok(Command::Info(Info::Rule(rules))), "{}", bits);
+	}
+}
+
+#[test]
+fn board_reads_stones_up_to_done() {
+	let board = Board::with_size(15);
+	let mut input = lines(&["7,7,1", "8,8,2", " 9,9,1 ", "DONE", "BEGIN"]);
+
+	assert_eq!(pbrain::read_board(&board, &mut input), Ok(vec![
+		(Position::with_size(7, 7, 15), true),
+		(Position::with_size(8, 8, 15), false),
+		(Position::with_size(9, 9, 15), true),
+	]));
+	// the lines after DONE are left for the command loop
+	assert_eq!(input.next().unwrap().unwrap(), "BEGIN");
+
+	assert!(pbrain::read_board(&board, &mut lines(&["7,7,1"])).is_err());
+	assert!(pbrain::read_board(&board, &mut lines(&["7,7", "DONE"])).is_err());
+	assert!(pbrain::read_board(&board, &mut lines(&["20,7,1", "DONE"])).is_err());
+}
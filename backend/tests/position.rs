@@ -0,0 +1,22 @@
+use backend::board::Board;
+use backend::position::Position;
+
+// `{"x":19,"y":19}` as a request sends it, for a 20x20 board.
+fn corner() -> Position {
+	serde_json::from_str(r#"{"x": 19, "y": 19}"#).unwrap()
+}
+
+#[test]
+fn deserialized_positions_print_on_large_boards() {
+	assert_eq!(corner().to_string(), "T19");
+	assert_eq!(Position::with_size(19, 19, 20).to_string(), "T19");
+}
+
+#[test]
+fn deserialized_positions_index_the_given_board() {
+	let board = Board::with_size(20);
+	let last = board.into_iter().last().unwrap();
+
+	assert_eq!(corner().to_u64(board.size()), last.to_u64(board.size()));
+	assert_eq!(Position::from_u64(corner().to_u64(20) as usize, 20), last);
+}
@@ -0,0 +1,81 @@
+use backend::board::Board;
+use backend::heuristic::Heuristic;
+use backend::piece::Piece;
+use backend::position::Position;
+use backend::renju;
+use backend::ruleset::RuleKind;
+
+fn board(black: &[(usize, usize)], white: &[(usize, usize)]) -> Board {
+	let mut board = Board::new();
+
+	for (stones, player) in [(black, Piece::Max), (white, Piece::Min)] {
+		for (x, y) in stones {
+			board.set_move(Position::new(*x, *y), player, Some(0));
+		}
+	}
+
+	board
+}
+
+fn forbidden(black: &[(usize, usize)], white: &[(usize, usize)], pos: (usize, usize)) -> bool {
+	let mut board = board(black, white);
+	let hash = board.hash();
+	let forbidden = renju::is_forbidden(&mut board, Position::new(pos.0, pos.1));
+
+	assert_eq!(board.hash(), hash, "the board is left unchanged");
+	forbidden
+}
+
+#[test]
+fn double_three_is_forbidden() {
+	let black = [(6, 9), (7, 9), (8, 7), (8, 8)];
+
+	assert!(forbidden(&black, &[], (8, 9)));
+	// a single three is fine
+	assert!(!forbidden(&black[..3], &[], (8, 9)));
+}
+
+#[test]
+fn double_four_is_forbidden() {
+	let black = [(8, 6), (8, 7), (8, 8), (5, 6), (6, 7), (7, 8)];
+
+	assert!(forbidden(&black, &[], (8, 9)));
+	// two fours on one line, `X_XXX_X`
+	assert!(forbidden(&[(4, 9), (6, 9), (7, 9), (10, 9)], &[], (8, 9)));
+}
+
+#[test]
+fn overline_is_forbidden() {
+	assert!(forbidden(&[(3, 9), (4, 9), (5, 9), (7, 9), (8, 9)], &[], (6, 9)));
+}
+
+#[test]
+fn five_wins_over_a_forbidden_shape() {
+	let fours = [(8, 6), (8, 7), (8, 8), (5, 6), (6, 7), (7, 8)];
+	let five: Vec<_> = fours.iter().chain(&[(4, 9), (5, 9), (6, 9), (7, 9)]).copied().collect();
+
+	assert!(!forbidden(&five, &[], (8, 9)));
+}
+
+#[test]
+fn three_that_only_extends_on_a_forbidden_cell_is_false() {
+	// `XX_` on row 9 makes a three through (8, 9), blocked on the right, so its
+	// only straight four is (5, 9), next to a column three and a diagonal three
+	let black = [(6, 9), (7, 9), (8, 7), (8, 8)];
+	let white = [(10, 9)];
+	let around = [(5, 7), (5, 8), (3, 7), (4, 8)];
+	let with_around: Vec<_> = black.iter().chain(&around).copied().collect();
+
+	assert!(forbidden(&black, &white, (8, 9)));
+	assert!(forbidden(&with_around, &white, (5, 9)));
+	assert!(!forbidden(&with_around, &white, (8, 9)));
+}
+
+#[test]
+fn only_black_is_restricted() {
+	let black = [(3, 9), (4, 9), (5, 9), (7, 9), (8, 9)];
+	let mut heuristic = Heuristic::from_board(&board(&black, &[]), &[0, 0], RuleKind::Renju.ruleset());
+
+	assert!(!heuristic.validate_move(Position::new(6, 9), Piece::Max));
+	assert!(heuristic.validate_move(Position::new(6, 9), Piece::Min));
+}
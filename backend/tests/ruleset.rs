@@ -0,0 +1,97 @@
+use backend::board::Board;
+use backend::heuristic::Heuristic;
+use backend::piece::{Piece, PieceWrap};
+use backend::position::Position;
+use backend::ruleset::RuleKind;
+
+const RULES: [RuleKind; 5] = [RuleKind::FortyTwo, RuleKind::Gomoku, RuleKind::Freestyle, RuleKind::NinukiRenju, RuleKind::Renju];
+
+// `stones` of `player` on row 9, by column.
+fn row(stones: &[usize], player: Piece) -> Board {
+	let mut board = Board::new();
+
+	for x in stones {
+		board.set_move(Position::new(*x, 9), player, Some(0));
+	}
+
+	board
+}
+
+fn winner(rules: RuleKind, board: &Board, player: Piece) -> Option<Piece> {
+	let mut heuristic = Heuristic::from_board(board, &[0, 0], rules.ruleset());
+
+	heuristic.get_heuristic();
+	heuristic.winner(player.get_opposite())
+}
+
+#[test]
+fn five_wins_everywhere() {
+	for rules in RULES {
+		for player in [Piece::Max, Piece::Min] {
+			assert_eq!(winner(rules, &row(&[3, 4, 5, 6, 7], player), player), Some(player), "{:?} {:?}", rules, player);
+		}
+	}
+}
+
+#[test]
+fn overlines_win_by_rule_set_and_player() {
+	// black, white
+	let expected = [
+		(RuleKind::FortyTwo, [true, true]),
+		(RuleKind::Gomoku, [false, false]),
+		(RuleKind::Freestyle, [true, true]),
+		(RuleKind::NinukiRenju, [false, true]),
+		(RuleKind::Renju, [false, true]),
+	];
+
+	for (rules, wins) in expected {
+		for (player, wins) in [Piece::Max, Piece::Min].into_iter().zip(wins) {
+			let board = row(&[3, 4, 5, 6, 7, 8], player);
+
+			assert_eq!(winner(rules, &board, player).is_some(), wins, "{:?} {:?}", rules, player);
+			assert_eq!(rules.ruleset().is_winning_length(6, player), wins, "{:?} {:?}", rules, player);
+		}
+	}
+}
+
+#[test]
+fn double_three_by_rule_set_and_player() {
+	// black, white
+	let expected = [
+		(RuleKind::FortyTwo, [false, false]),
+		(RuleKind::Gomoku, [true, true]),
+		(RuleKind::Freestyle, [true, true]),
+		(RuleKind::NinukiRenju, [false, true]),
+		(RuleKind::Renju, [false, true]),
+	];
+
+	for (rules, allowed) in expected {
+		for (player, allowed) in [Piece::Max, Piece::Min].into_iter().zip(allowed) {
+			let mut board = Board::new();
+
+			for (x, y) in [(6, 9), (7, 9), (8, 7), (8, 8)] {
+				board.set_move(Position::new(x, y), player, Some(0));
+			}
+
+			let mut heuristic = Heuristic::from_board(&board, &[0, 0], rules.ruleset());
+
+			assert_eq!(heuristic.validate_move(Position::new(8, 9), player), allowed, "{:?} {:?}", rules, player);
+		}
+	}
+}
+
+#[test]
+fn captures_by_rule_set() {
+	let mut board = row(&[5], Piece::Max);
+
+	board.set_move(Position::new(6, 9), Piece::Min, Some(0));
+	board.set_move(Position::new(7, 9), Piece::Min, Some(0));
+
+	for rules in RULES {
+		let ruleset = rules.ruleset();
+		let capture_map = ruleset.capture_map(&board, Position::new(8, 9), Piece::Max);
+
+		assert_eq!(ruleset.captures(), capture_map != 0, "{:?}", rules);
+		assert_eq!(ruleset.capture_win().is_some(), ruleset.captures(), "{:?}", rules);
+	}
+}
@@ -0,0 +1,97 @@
+use std::time::{Duration, Instant};
+
+use backend::CalculateRequest;
+use backend::board::Board;
+use backend::minimax::{self, GomokuSolver};
+use backend::piece::Piece;
+use backend::position::Position;
+
+fn solver(stones: &[(usize, usize)], depth: usize) -> GomokuSolver {
+	let mut board = Board::new();
+
+	for (x, y) in stones {
+		board.set_move(Position::new(*x, *y), Piece::Max, Some(0));
+	}
+
+	let mut solver = GomokuSolver::new(board, [0, 0], Piece::Max, depth);
+
+	solver.is_hint = Some(true);
+	solver
+}
+
+#[test]
+fn mate_in_counts_moves_past_transposition_cutoffs() {
+	// an open three on row 9: open four, then five whatever white does
+	for depth in [3, 4, 5] {
+		let mut solver = solver(&[(8, 9), (9, 9), (10, 9)], depth);
+		let res = solver.solve().unwrap();
+
+		assert_eq!(res.score, f32::INFINITY);
+		assert_eq!(minimax::resolve_mate_depth(&res, solver.depth), Some(2), "depth {}", depth);
+	}
+}
+
+#[test]
+fn mate_in_zero_for_a_five_on_the_board() {
+	let mut solver = solver(&[(7, 9), (8, 9), (9, 9), (10, 9)], 3);
+	let res = solver.solve().unwrap();
+
+	assert_eq!(minimax::resolve_mate_depth(&res, solver.depth), Some(0));
+}
+
+#[test]
+fn threat_search_shares_the_time_budget() {
+	let budget = Duration::from_millis(300);
+	let mut board = Board::new();
+
+	// black and white in turn, nobody has a forced win
+	for (i, (x, y)) in [(9, 9), (10, 10), (8, 10), (10, 8), (9, 11), (7, 9), (11, 9), (9, 7)].iter().enumerate() {
+		board.set_move(Position::new(*x, *y), if i % 2 == 0 {Piece::Max} else {Piece::Min}, Some(0));
+	}
+
+	let mut solver = GomokuSolver::new(board, [0, 0], Piece::Max, 20);
+
+	solver.is_hint = Some(true);
+	solver.max_time = Some(budget);
+	solver.threat_depth = Some(12);
+
+	let started = Instant::now();
+
+	solver.solve().unwrap();
+
+	assert!(started.elapsed() < budget * 3 / 2, "took {:?}", started.elapsed());
+}
+
+fn request(threads: usize) -> CalculateRequest {
+	serde_json::from_value(serde_json::json!({
+		"board": {"180": 0, "200": 1, "162": 0, "181": 1},
+		"depth": 4,
+		"player": 1,
+		"captures": [0, 0],
+		"threads": threads,
+	})).unwrap()
+}
+
+// The single threaded search Lazy SMP started from: `threads: 1` must keep its result.
+#[test]
+fn one_thread_searches_like_before() {
+	let mut solver = GomokuSolver::from_request(&request(1)).unwrap();
+	let res = solver.solve().unwrap();
+
+	assert_eq!(solver.threads, 1);
+	assert_eq!(res.position, Position::new(10, 11));
+	assert_eq!(res.score, 9.947369);
+
+	let mut plain = GomokuSolver::new(solver.board.clone(), [0, 0], Piece::Max, 4);
+
+	plain.is_hint = Some(true);
+	assert_eq!(plain.solve().unwrap().score, res.score);
+}
+
+#[test]
+fn thread_counts_stop_at_the_core_count() {
+	let cores = std::thread::available_parallelism().unwrap().get();
+
+	assert_eq!(GomokuSolver::from_request(&request(100000)).unwrap().threads, cores);
+	assert_eq!(GomokuSolver::from_request(&request(0)).unwrap().threads, 1);
+}
@@ -0,0 +1,106 @@
+use backend::board::Board;
+use backend::piece::Piece;
+use backend::position::Position;
+use backend::ruleset::RuleKind;
+use backend::threat_space::{ThreatKind, ThreatSolver};
+
+fn board(stones: &[((usize, usize), Piece)]) -> Board {
+	let mut board = Board::new();
+
+	for ((x, y), player) in stones {
+		board.set_move(Position::new(*x, *y), *player, Some(0));
+	}
+
+	board
+}
+
+fn solver(stones: &[((usize, usize), Piece)], rules: RuleKind) -> ThreatSolver {
+	ThreatSolver::new(&board(stones), [0, 0], Piece::Max, rules.ruleset())
+}
+
+// Black split threes on column 9 and row 9, crossing on the empty (9,9).
+fn double_three() -> Vec<((usize, usize), Piece)> {
+	vec![((8, 9), Piece::Max), ((10, 9), Piece::Max), ((9, 8), Piece::Max), ((9, 10), Piece::Max)]
+}
+
+// Black twos on column 9 and row 9, both made open threes by (9,9).
+fn adjacent_double_three() -> Vec<((usize, usize), Piece)> {
+	vec![((7, 9), Piece::Max), ((8, 9), Piece::Max), ((9, 7), Piece::Max), ((9, 8), Piece::Max)]
+}
+
+#[test]
+fn vcf_wins_with_a_double_four() {
+	// a closed three on row 9 and one on column 12, both four moves meet on (12,9)
+	let stones = [
+		((9, 9), Piece::Max), ((10, 9), Piece::Max), ((11, 9), Piece::Max), ((8, 9), Piece::Min),
+		((12, 10), Piece::Max), ((12, 11), Piece::Max), ((12, 12), Piece::Max), ((12, 13), Piece::Min),
+	];
+
+	let line = solver(&stones, RuleKind::Freestyle).solve(ThreatKind::Vcf, 3).unwrap();
+
+	assert_eq!(line.first(), Some(&Position::new(12, 9)));
+	assert_eq!(line.len(), 3);
+}
+
+#[test]
+fn vcf_finds_no_win_without_fours() {
+	for stones in [double_three(), adjacent_double_three()] {
+		assert_eq!(solver(&stones, RuleKind::Freestyle).solve(ThreatKind::Vcf, 4), None);
+	}
+}
+
+#[test]
+fn vct_wins_with_a_double_three() {
+	for (stones, rules) in [(double_three(), RuleKind::Freestyle), (double_three(), RuleKind::Gomoku), (adjacent_double_three(), RuleKind::Freestyle)] {
+		for depth in [2, 3, 4] {
+			let line = solver(&stones, rules).solve(ThreatKind::Vct, depth);
+
+			assert_eq!(line, Some(vec![Position::new(9, 9)]), "{:?} at depth {}", rules, depth);
+		}
+	}
+}
+
+#[test]
+fn double_three_is_no_win_where_it_is_forbidden() {
+	let mut solver = solver(&adjacent_double_three(), RuleKind::FortyTwo);
+
+	assert_ne!(solver.solve(ThreatKind::Vct, 4).and_then(|line| line.first().copied()), Some(Position::new(9, 9)));
+}
+
+// Four on row 9 from (5,9). Once (9,9) makes it five, white takes (7,9) and (7,10) back on (7,8).
+fn breakable_four() -> Vec<((usize, usize), Piece)> {
+	vec![
+		((5, 9), Piece::Max), ((6, 9), Piece::Max), ((7, 9), Piece::Max), ((8, 9), Piece::Max), ((4, 9), Piece::Min),
+		((7, 10), Piece::Max), ((7, 11), Piece::Min),
+	]
+}
+
+#[test]
+fn five_that_a_capture_breaks_needs_another_move() {
+	// white takes the five apart, black fills the captured square again
+	let line = solver(&breakable_four(), RuleKind::FortyTwo).solve(ThreatKind::Vcf, 1);
+
+	assert_eq!(line, Some(vec![Position::new(9, 9), Position::new(7, 8), Position::new(7, 9)]));
+
+	let line = solver(&breakable_four(), RuleKind::Freestyle).solve(ThreatKind::Vcf, 1);
+
+	assert_eq!(line, Some(vec![Position::new(9, 9)]));
+}
+
+#[test]
+fn five_that_a_capture_breaks_loses_to_the_capture_win() {
+	let mut solver = solver(&breakable_four(), RuleKind::FortyTwo);
+
+	solver.captures = [0, 4];
+
+	assert_eq!(solver.solve(ThreatKind::Vcf, 4), None);
+}
+
+#[test]
+fn stopped_search_gives_up() {
+	let mut solver = solver(&double_three(), RuleKind::Freestyle);
+
+	solver.stop.store(true, std::sync::atomic::Ordering::Relaxed);
+
+	assert_eq!(solver.solve(ThreatKind::Vct, 4), None);
+}
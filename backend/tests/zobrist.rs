@@ -0,0 +1,37 @@
+use backend::board::Board;
+use backend::piece::Piece;
+use backend::position::Position;
+
+// The same stones read back through `Board::from_map`, which hashes from scratch.
+fn rehashed(board: &Board) -> Board {
+	let map = board.into_iter()
+		.map(|pos| (pos.to_u64(board.size()).to_string(), serde_json::Value::from(board[&pos] as i8)))
+		.collect();
+
+	Board::from_map(&map, board.size()).unwrap()
+}
+
+#[test]
+fn incremental_hash_matches_a_fresh_one_across_captures() {
+	let mut board = Board::new();
+
+	// white pairs on (6,5),(7,5) and (5,6),(5,7), both flanked by black from (5,5)
+	for (x, y, player) in [(8, 5, Piece::Max), (6, 5, Piece::Min), (7, 5, Piece::Min), (5, 8, Piece::Max), (5, 6, Piece::Min), (5, 7, Piece::Min)] {
+		board.set_move(Position::new(x, y), player, None);
+	}
+
+	let before = board.hash();
+
+	assert_eq!(before, rehashed(&board).hash());
+
+	let undo = board.make_move(Position::new(5, 5), Piece::Max, None);
+
+	assert_eq!(undo.capture_count(), 2);
+	assert_ne!(board.hash(), before);
+	assert_eq!(board.hash(), rehashed(&board).hash());
+
+	board.unmake_move(&undo);
+
+	assert_eq!(board.hash(), before);
+	assert_eq!(board.hash(), rehashed(&board).hash());
+}